@@ -0,0 +1,407 @@
+use crate::entities::credentials::PasswordScheme;
+use crate::entities::dictionary::LocaleShare;
+use crate::entities::identity::DomainShare;
+use crate::entities::number_plan::NumberRange;
+use std::fs;
+use std::str::FromStr;
+
+/// Inclusive range of counts, written as `{min}-{max}` or a single number.
+#[derive(Debug, Clone, Copy)]
+pub struct CountRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl FromStr for CountRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<CountRange, String> {
+        let (min, max) = value.split_once('-').unwrap_or((value, value));
+        let range = CountRange {
+            min: min.trim().parse().map_err(|_| "Bad range start")?,
+            max: max.trim().parse().map_err(|_| "Bad range end")?,
+        };
+        if range.min > range.max {
+            return Err(format!("Empty range '{}'", value));
+        }
+        Ok(range)
+    }
+}
+
+/// Customer profile driving correlated properties of its contracts. Set in
+/// the scenario file as `segment.{name}.{field} = value`, naming a segment
+/// that does not exist yet adds one.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub name: String,
+    /// Relative share of generated contracts, zero disables the segment.
+    pub share: u32,
+    /// Contracts are companies with identification numbers and DID blocks.
+    pub company: bool,
+    pub participants: CountRange,
+    pub numbers_per_participant: CountRange,
+    /// Chance in percent that a participant other than the contract owner is
+    /// an admin rather than a user.
+    pub admin_percent: u8,
+    /// How much more traffic its numbers get compared to other segments.
+    pub call_weight: f64,
+    /// Chance in percent that a call goes abroad.
+    pub international_percent: u8,
+    /// Quantity of each item on an invoice.
+    pub invoice_quantity: CountRange,
+    /// Simultaneous calls a participant can have, zero for unlimited.
+    pub participant_channels: CountRange,
+    /// Simultaneous calls all participants of a contract can have together,
+    /// zero for unlimited.
+    pub contract_channels: CountRange,
+}
+
+impl Segment {
+    fn new(name: &str) -> Segment {
+        Segment {
+            name: name.to_string(),
+            share: 0,
+            company: false,
+            participants: CountRange { min: 1, max: 1 },
+            numbers_per_participant: CountRange { min: 1, max: 1 },
+            admin_percent: 0,
+            call_weight: 1.0,
+            international_percent: 5,
+            invoice_quantity: CountRange { min: 1, max: 1 },
+            participant_channels: CountRange { min: 2, max: 2 },
+            contract_channels: CountRange { min: 10, max: 10 },
+        }
+    }
+
+    fn defaults() -> Vec<Segment> {
+        vec![
+            Segment {
+                share: 70,
+                participants: CountRange { min: 1, max: 3 },
+                numbers_per_participant: CountRange { min: 1, max: 2 },
+                admin_percent: 30,
+                participant_channels: CountRange { min: 1, max: 2 },
+                contract_channels: CountRange { min: 2, max: 4 },
+                ..Segment::new("residential")
+            },
+            Segment {
+                share: 20,
+                company: true,
+                participants: CountRange { min: 2, max: 8 },
+                numbers_per_participant: CountRange { min: 1, max: 2 },
+                admin_percent: 30,
+                call_weight: 3.0,
+                international_percent: 10,
+                invoice_quantity: CountRange { min: 1, max: 5 },
+                participant_channels: CountRange { min: 2, max: 2 },
+                contract_channels: CountRange { min: 4, max: 10 },
+                ..Segment::new("smb")
+            },
+            Segment {
+                share: 7,
+                company: true,
+                participants: CountRange { min: 10, max: 40 },
+                numbers_per_participant: CountRange { min: 1, max: 3 },
+                admin_percent: 15,
+                call_weight: 6.0,
+                international_percent: 25,
+                invoice_quantity: CountRange { min: 5, max: 30 },
+                participant_channels: CountRange { min: 2, max: 4 },
+                contract_channels: CountRange { min: 20, max: 60 },
+                ..Segment::new("enterprise")
+            },
+            Segment {
+                share: 3,
+                company: true,
+                participants: CountRange { min: 1, max: 3 },
+                numbers_per_participant: CountRange { min: 5, max: 20 },
+                admin_percent: 60,
+                call_weight: 10.0,
+                international_percent: 30,
+                invoice_quantity: CountRange { min: 10, max: 60 },
+                participant_channels: CountRange { min: 0, max: 0 },
+                contract_channels: CountRange { min: 30, max: 100 },
+                ..Segment::new("reseller")
+            },
+        ]
+    }
+
+    fn set(&mut self, key: &str, field: &str, value: &str) {
+        match field {
+            "share" => self.share = parse_value(key, value),
+            "company" => self.company = parse_value(key, value),
+            "participants" => self.participants = parse_value(key, value),
+            "numbers_per_participant" => self.numbers_per_participant = parse_value(key, value),
+            "admin_percent" => self.admin_percent = parse_value(key, value),
+            "call_weight" => self.call_weight = parse_value(key, value),
+            "international_percent" => self.international_percent = parse_value(key, value),
+            "invoice_quantity" => self.invoice_quantity = parse_value(key, value),
+            "participant_channels" => self.participant_channels = parse_value(key, value),
+            "contract_channels" => self.contract_channels = parse_value(key, value),
+            _ => panic!("Unknown scenario key '{}'", key),
+        }
+    }
+}
+
+/// Tunables of a generation run. Loaded from a plain `key = value` file,
+/// blank lines and lines starting with `#` are ignored. Missing keys keep
+/// their default value.
+#[derive(Debug)]
+pub struct Scenario {
+    /// Chance in percent that a contract ends before now.
+    pub contract_churn_percent: u8,
+    /// Chance in percent that a participant leaves before its contract ends.
+    pub participant_churn_percent: u8,
+    /// National number ranges new numbers are allocated from, in the country
+    /// of the contract's locale. Defaults cover the embedded dictionaries.
+    pub number_ranges: Vec<NumberRange>,
+    /// Size of the contiguous DID block given to every company contract,
+    /// no blocks are allocated when zero.
+    pub company_did_block_size: u32,
+    /// Chance in percent that a number is released before its participant leaves.
+    pub number_churn_percent: u8,
+    /// Days a released number stays in quarantine before it can be reused.
+    pub number_quarantine_days: u32,
+    /// Relative frequency of pending, approved, rejected and expired number
+    /// requests.
+    pub number_request_outcome_weights: Vec<u32>,
+    /// Days an undecided number request stays pending before it expires.
+    pub number_request_expiry_days: u32,
+    /// Chance in percent that a new assignment reuses a number out of quarantine
+    /// instead of a fresh one.
+    pub number_reuse_percent: u8,
+    /// Rewrite outgoing calls placed after a participant's `balance_limit`
+    /// was reached in the billing period to a blocked disposition.
+    pub block_over_balance_limit: bool,
+    /// Days after invoice maturity at which payment reminders are sent.
+    pub dunning_reminder_days: Vec<u32>,
+    /// Days after maturity of a still unpaid invoice at which the contract's
+    /// numbers get suspended.
+    pub dunning_suspend_after_days: u32,
+    /// Days after maturity of a still unpaid invoice at which the contract is
+    /// terminated, never when unset.
+    pub dunning_terminate_after_days: Option<u32>,
+    /// Exponent of the Zipf distribution of calls over numbers, higher values
+    /// concentrate more traffic on the busiest numbers.
+    pub traffic_zipf_exponent: f64,
+    /// Relative call volume for each hour of the day, Czech local time.
+    pub traffic_hour_weights: Vec<u32>,
+    /// Relative call volume for each day of the week starting with Monday.
+    pub traffic_weekday_weights: Vec<u32>,
+    /// Relative call volume on Czech public holidays, replaces the weekday weight.
+    pub traffic_holiday_weight: u32,
+    /// Median of the log-normal call duration in seconds.
+    pub call_duration_median_seconds: f64,
+    /// Shape of the log-normal call duration, the standard deviation of its logarithm.
+    pub call_duration_sigma: f64,
+    /// Relative frequency of the ANSWERED, NO ANSWER, BUSY, FAILED and
+    /// CONGESTION dispositions, in this order.
+    pub call_disposition_weights: Vec<u32>,
+    /// Chance in percent that an international call from a number with
+    /// `foreign_block` is kept as a failed attempt, the rest become domestic calls.
+    pub foreign_block_attempt_percent: u8,
+    /// Chance in percent that an answered call gets transferred to another
+    /// number, repeatedly for the transferred leg.
+    pub call_transfer_percent: u8,
+    /// Chance in percent that an answered incoming call is forwarded to
+    /// another number.
+    pub call_forward_percent: u8,
+    /// Locales contracts are generated in with their relative share.
+    pub locales: Vec<LocaleShare>,
+    /// Directory with `{locale}.dict` files overriding the embedded dictionaries.
+    pub dictionary_dir: Option<String>,
+    /// Mail domains of personal e-mail addresses with their relative share,
+    /// each locale's free mail providers when empty.
+    pub email_domains: Vec<DomainShare>,
+    /// Hash stored as participant password, `bcrypt` or `argon2id`.
+    pub password_scheme: PasswordScheme,
+    /// Cost factor of bcrypt hashes, kept low so large runs stay fast.
+    pub bcrypt_cost: u32,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    /// Digest realm of the SIP HA1 stored as VoIP number password.
+    pub sip_realm: String,
+    /// Share of accounts whose plaintext password is exported to
+    /// `credentials.csv` in the export directory.
+    pub credentials_percent: u8,
+    /// Generate the `participant_permission` table matching access levels.
+    pub generate_permissions: bool,
+    /// SMS records generated for every generated call.
+    pub sms_per_call: f64,
+    /// Data sessions generated for every generated call.
+    pub data_sessions_per_call: f64,
+    /// Chance in percent that a contract changes its plan, tried again after
+    /// every change up to three times.
+    pub plan_change_percent: u8,
+    /// Customer segments contracts are drawn from by their share.
+    pub segments: Vec<Segment>,
+}
+
+impl Default for Scenario {
+    fn default() -> Scenario {
+        Scenario {
+            contract_churn_percent: 10,
+            participant_churn_percent: 5,
+            number_ranges: [
+                "420:500000000-599999999",
+                "421:900000000-949999999",
+                "49:15100000000-15199999999",
+                "1:2012000000-2019999999",
+            ]
+            .iter()
+            .map(|range| range.parse().unwrap())
+            .collect(),
+            company_did_block_size: 0,
+            number_churn_percent: 15,
+            number_quarantine_days: 90,
+            number_request_outcome_weights: vec![10, 65, 15, 10],
+            number_request_expiry_days: 30,
+            number_reuse_percent: 50,
+            block_over_balance_limit: false,
+            dunning_reminder_days: vec![7, 14, 30],
+            dunning_suspend_after_days: 30,
+            dunning_terminate_after_days: None,
+            traffic_zipf_exponent: 1.1,
+            traffic_hour_weights: vec![
+                1, 1, 1, 1, 1, 2, 4, 8, 14, 16, 16, 15, 12, 14, 15, 14, 12, 10, 8, 7, 6, 4, 3, 2,
+            ],
+            traffic_weekday_weights: vec![100, 100, 100, 100, 90, 35, 25],
+            traffic_holiday_weight: 25,
+            call_duration_median_seconds: 60.0,
+            call_duration_sigma: 1.1,
+            call_disposition_weights: vec![70, 15, 8, 4, 3],
+            foreign_block_attempt_percent: 10,
+            call_transfer_percent: 0,
+            call_forward_percent: 0,
+            locales: vec!["cs_CZ".parse().unwrap()],
+            dictionary_dir: None,
+            email_domains: vec![],
+            password_scheme: PasswordScheme::Bcrypt,
+            bcrypt_cost: 4,
+            argon2_memory_kib: 1024,
+            argon2_iterations: 1,
+            sip_realm: String::from("dais"),
+            credentials_percent: 10,
+            generate_permissions: false,
+            plan_change_percent: 30,
+            sms_per_call: 0.4,
+            data_sessions_per_call: 0.2,
+            segments: Segment::defaults(),
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> T {
+    value
+        .parse::<T>()
+        .unwrap_or_else(|_| panic!("Invalid value '{}' for scenario key '{}'", value, key))
+}
+
+fn parse_list<T: std::str::FromStr>(key: &str, value: &str) -> Vec<T> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| parse_value(key, v))
+        .collect()
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Scenario {
+        let content = fs::read_to_string(path).expect("Failed to read scenario file");
+        let mut scenario = Scenario::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .unwrap_or_else(|| panic!("Malformed scenario line '{}'", line));
+            let (key, value) = (key.trim(), value.trim());
+            if let Some((name, field)) =
+                key.strip_prefix("segment.").and_then(|k| k.split_once('.'))
+            {
+                let idx = match scenario.segments.iter().position(|s| s.name == name) {
+                    Some(idx) => idx,
+                    None => {
+                        scenario.segments.push(Segment::new(name));
+                        scenario.segments.len() - 1
+                    }
+                };
+                scenario.segments[idx].set(key, field, value);
+                continue;
+            }
+            match key {
+                "contract_churn_percent" => {
+                    scenario.contract_churn_percent = parse_value(key, value)
+                }
+                "participant_churn_percent" => {
+                    scenario.participant_churn_percent = parse_value(key, value)
+                }
+                "number_ranges" => scenario.number_ranges = parse_list(key, value),
+                "company_did_block_size" => {
+                    scenario.company_did_block_size = parse_value(key, value)
+                }
+                "number_churn_percent" => scenario.number_churn_percent = parse_value(key, value),
+                "number_request_outcome_weights" => {
+                    scenario.number_request_outcome_weights = parse_list(key, value)
+                }
+                "number_request_expiry_days" => {
+                    scenario.number_request_expiry_days = parse_value(key, value)
+                }
+                "number_quarantine_days" => {
+                    scenario.number_quarantine_days = parse_value(key, value)
+                }
+                "number_reuse_percent" => scenario.number_reuse_percent = parse_value(key, value),
+                "block_over_balance_limit" => {
+                    scenario.block_over_balance_limit = parse_value(key, value)
+                }
+                "dunning_reminder_days" => scenario.dunning_reminder_days = parse_list(key, value),
+                "dunning_suspend_after_days" => {
+                    scenario.dunning_suspend_after_days = parse_value(key, value)
+                }
+                "dunning_terminate_after_days" => {
+                    scenario.dunning_terminate_after_days = Some(parse_value(key, value))
+                }
+                "traffic_zipf_exponent" => scenario.traffic_zipf_exponent = parse_value(key, value),
+                "traffic_hour_weights" => scenario.traffic_hour_weights = parse_list(key, value),
+                "traffic_weekday_weights" => {
+                    scenario.traffic_weekday_weights = parse_list(key, value)
+                }
+                "traffic_holiday_weight" => {
+                    scenario.traffic_holiday_weight = parse_value(key, value)
+                }
+                "call_duration_median_seconds" => {
+                    scenario.call_duration_median_seconds = parse_value(key, value)
+                }
+                "call_duration_sigma" => scenario.call_duration_sigma = parse_value(key, value),
+                "call_disposition_weights" => {
+                    scenario.call_disposition_weights = parse_list(key, value)
+                }
+                "foreign_block_attempt_percent" => {
+                    scenario.foreign_block_attempt_percent = parse_value(key, value)
+                }
+                "call_transfer_percent" => scenario.call_transfer_percent = parse_value(key, value),
+                "call_forward_percent" => scenario.call_forward_percent = parse_value(key, value),
+                "locales" => scenario.locales = parse_list(key, value),
+                "dictionary_dir" => scenario.dictionary_dir = Some(value.to_string()),
+                "email_domains" => scenario.email_domains = parse_list(key, value),
+                "password_scheme" => scenario.password_scheme = parse_value(key, value),
+                "bcrypt_cost" => scenario.bcrypt_cost = parse_value(key, value),
+                "argon2_memory_kib" => scenario.argon2_memory_kib = parse_value(key, value),
+                "argon2_iterations" => scenario.argon2_iterations = parse_value(key, value),
+                "sip_realm" => scenario.sip_realm = value.to_string(),
+                "credentials_percent" => scenario.credentials_percent = parse_value(key, value),
+                "sms_per_call" => scenario.sms_per_call = parse_value(key, value),
+                "data_sessions_per_call" => {
+                    scenario.data_sessions_per_call = parse_value(key, value)
+                }
+                "plan_change_percent" => scenario.plan_change_percent = parse_value(key, value),
+                "generate_permissions" => scenario.generate_permissions = parse_value(key, value),
+                _ => panic!("Unknown scenario key '{}'", key),
+            }
+        }
+        scenario
+    }
+}
@@ -0,0 +1,114 @@
+use crate::config::Scenario;
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use fake::faker::boolean::en::Boolean;
+use fake::{Fake, StringFaker};
+use md5::{Digest, Md5};
+use std::str::FromStr;
+
+pub const CREDENTIAL_PARTICIPANT: &str = "PARTICIPANT";
+pub const CREDENTIAL_SIP: &str = "SIP";
+
+/// Hash stored as a participant's password.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PasswordScheme {
+    Bcrypt,
+    Argon2id,
+}
+
+impl FromStr for PasswordScheme {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<PasswordScheme, String> {
+        match value {
+            "bcrypt" => Ok(PasswordScheme::Bcrypt),
+            "argon2id" => Ok(PasswordScheme::Argon2id),
+            _ => Err(format!("Unknown password scheme '{}'", value)),
+        }
+    }
+}
+
+/// Plaintext password of a generated account, written to the side file for
+/// login and SIP registration tests.
+pub struct TestCredential {
+    pub kind: &'static str,
+    pub id: u32,
+    pub username: String,
+    pub password: String,
+}
+
+/// Generates account passwords and returns only their hashes, keeping the
+/// plaintext of a random share of accounts in `recorded`.
+pub struct Credentials {
+    scheme: PasswordScheme,
+    bcrypt_cost: u32,
+    argon2: Argon2<'static>,
+    sip_realm: String,
+    recorded_percent: u8,
+    plaintext: StringFaker<std::ops::Range<usize>>,
+    pub recorded: Vec<TestCredential>,
+}
+
+impl Credentials {
+    pub fn new(scenario: &Scenario) -> Credentials {
+        let params = Params::new(
+            scenario.argon2_memory_kib,
+            scenario.argon2_iterations,
+            1,
+            None,
+        )
+        .expect("Invalid argon2 parameters");
+        Credentials {
+            scheme: scenario.password_scheme,
+            bcrypt_cost: scenario.bcrypt_cost,
+            argon2: Argon2::new(Algorithm::Argon2id, Version::V0x13, params),
+            sip_realm: scenario.sip_realm.clone(),
+            recorded_percent: scenario.credentials_percent,
+            plaintext: StringFaker::with(
+                String::from("abcdefghijkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789")
+                    .into_bytes(),
+                12..13,
+            ),
+            recorded: vec![],
+        }
+    }
+
+    fn password(&mut self, kind: &'static str, id: u32, username: &str) -> String {
+        let password: String = self.plaintext.fake();
+        if Boolean(self.recorded_percent).fake() {
+            self.recorded.push(TestCredential {
+                kind,
+                id,
+                username: username.to_string(),
+                password: password.clone(),
+            });
+        }
+        password
+    }
+
+    /// Password hash of a new participant in the configured scheme.
+    pub fn participant_password(&mut self, pid: u32, login: &str) -> String {
+        let password = self.password(CREDENTIAL_PARTICIPANT, pid, login);
+        match self.scheme {
+            PasswordScheme::Bcrypt => {
+                bcrypt::hash(&password, self.bcrypt_cost).expect("Failed to hash password")
+            }
+            PasswordScheme::Argon2id => {
+                let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+                    .expect("Failed to encode salt");
+                self.argon2
+                    .hash_password(password.as_bytes(), &salt)
+                    .expect("Failed to hash password")
+                    .to_string()
+            }
+        }
+    }
+
+    /// SIP digest HA1 of a new number's account, the hex MD5 of
+    /// `{username}:{realm}:{password}`.
+    pub fn sip_password(&mut self, nid: u32, username: &str) -> String {
+        let password = self.password(CREDENTIAL_SIP, nid, username);
+        let digest = Md5::digest(format!("{}:{}:{}", username, self.sip_realm, password));
+        format!("{:x}", digest)
+    }
+}
@@ -0,0 +1,72 @@
+use fake::Fake;
+
+/// Weight of each IČO digit in the checksum, the eighth digit is the check digit.
+const ICO_WEIGHTS: [u32; 7] = [8, 7, 6, 5, 4, 3, 2];
+
+fn ico_check_digit(digits: &[u32]) -> u32 {
+    let sum: u32 = digits
+        .iter()
+        .zip(ICO_WEIGHTS.iter())
+        .map(|(d, w)| d * w)
+        .sum();
+    (11 - sum % 11) % 10
+}
+
+/// Eight digit company identification number with a valid mod 11 check digit.
+/// The leading digit is never zero so the number survives an integer column.
+pub fn ico() -> u32 {
+    let mut digits: Vec<u32> = vec![(1..10).fake()];
+    digits.extend((0..6).map(|_| (0..10).fake::<u32>()));
+    let check = ico_check_digit(&digits);
+    digits
+        .iter()
+        .chain(std::iter::once(&check))
+        .fold(0, |n, d| n * 10 + d)
+}
+
+/// VAT number of a legal person, the IČO prefixed with the country code.
+pub fn dic(ico: u32) -> String {
+    format!("CZ{:08}", ico)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(ico: &str) -> Vec<u32> {
+        ico.chars().filter_map(|c| c.to_digit(10)).collect()
+    }
+
+    fn is_valid_ico(ico: u32) -> bool {
+        let digits = digits(&format!("{:08}", ico));
+        digits.len() == 8 && ico_check_digit(&digits[..7]) == digits[7]
+    }
+
+    #[test]
+    fn check_digit_of_known_icos() {
+        // Remainder 0 gives check digit 1, remainder 1 gives 0.
+        assert_eq!(ico_check_digit(&digits("2559664")), 1);
+        assert_eq!(ico_check_digit(&digits("2708244")), 0);
+        assert_eq!(ico_check_digit(&digits("0000694")), 7);
+        assert!(is_valid_ico(25596641));
+        assert!(is_valid_ico(27082440));
+        assert!(is_valid_ico(6947));
+        assert!(!is_valid_ico(25596642));
+        assert!(!is_valid_ico(123_456_789));
+    }
+
+    #[test]
+    fn dic_is_the_padded_ico() {
+        assert_eq!(dic(25596641), "CZ25596641");
+        assert_eq!(dic(6947), "CZ00006947");
+    }
+
+    #[test]
+    fn generated_icos_are_valid() {
+        for _ in 0..10_000 {
+            let ico = ico();
+            assert!(is_valid_ico(ico), "{}", ico);
+            assert_eq!(dic(ico), format!("CZ{:08}", ico));
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use super::text_column;
+use fake::faker::boolean::en::Boolean;
+use fake::Fake;
+use rand::distributions::WeightedIndex;
+use rand_distr::Distribution;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Longest dictionary value, the narrowest text column it ends up in.
+const MAX_VALUE_LEN: usize = 100;
+
+/// Datasets compiled into the generator, a file in the dictionary directory
+/// named after the locale takes precedence.
+const EMBEDDED: [(&str, &str); 4] = [
+    ("cs_CZ", include_str!("dictionaries/cs_CZ.dict")),
+    ("sk_SK", include_str!("dictionaries/sk_SK.dict")),
+    ("de_DE", include_str!("dictionaries/de_DE.dict")),
+    ("en_US", include_str!("dictionaries/en_US.dict")),
+];
+
+/// Locale with its share of generated contracts, written as `{locale}:{weight}`
+/// in the scenario file. A missing weight counts as 1.
+#[derive(Debug, Clone)]
+pub struct LocaleShare {
+    pub locale: String,
+    pub weight: u32,
+}
+
+impl FromStr for LocaleShare {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<LocaleShare, String> {
+        let (locale, weight) = parse_share(value)?;
+        Ok(LocaleShare { locale, weight })
+    }
+}
+
+/// Value and weight of a `{value}:{weight}` scenario entry.
+pub fn parse_share(value: &str) -> Result<(String, u32), String> {
+    let (name, weight) = match value.split_once(':') {
+        Some((name, weight)) => (
+            name,
+            weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("Bad weight in '{}'", value))?,
+        ),
+        None => (value, 1),
+    };
+    Ok((name.trim().to_string(), weight))
+}
+
+/// Values of one dictionary section drawn by their relative frequency.
+struct Weighted<T> {
+    values: Vec<T>,
+    index: WeightedIndex<u32>,
+}
+
+impl<T> Weighted<T> {
+    fn pick(&self) -> &T {
+        &self.values[self.index.sample(&mut rand::thread_rng())]
+    }
+}
+
+pub struct Municipality {
+    pub city: String,
+    pub district: String,
+    pub postal_code: i32,
+}
+
+pub struct PostalAddress {
+    pub city: String,
+    pub district: String,
+    pub street: String,
+    pub house_number: i32,
+    pub postal_code: i32,
+}
+
+/// Names, companies and addresses of one locale. Loaded from a text file with
+/// `key = value` header lines followed by `[section]`s of `value = weight`
+/// entries, fields of a value separated by `|`.
+pub struct Dictionary {
+    pub locale: String,
+    /// ISO 3166 code of the country, e.g. `CZ`.
+    pub country: String,
+    pub phone_country_code: u16,
+    mobile_prefixes: Weighted<String>,
+    male_first_names: Weighted<String>,
+    female_first_names: Weighted<String>,
+    /// Male and female form of each surname.
+    surnames: Weighted<(String, String)>,
+    company_words: Weighted<String>,
+    legal_forms: Weighted<String>,
+    email_domains: Weighted<String>,
+    company_domain: Weighted<String>,
+    municipalities: Weighted<Municipality>,
+    streets: Weighted<String>,
+}
+
+type Entries = Vec<(Vec<String>, u32)>;
+
+fn section<T>(
+    name: &str,
+    sections: &mut HashMap<String, Entries>,
+    key: &str,
+    parse: fn(&[String]) -> Option<T>,
+) -> Weighted<T> {
+    let entries = sections
+        .remove(key)
+        .unwrap_or_else(|| panic!("Dictionary {} has no [{}] section", name, key));
+    let mut values: Vec<T> = vec![];
+    let mut weights: Vec<u32> = vec![];
+    for (fields, weight) in entries {
+        let value = parse(&fields).unwrap_or_else(|| {
+            panic!(
+                "Malformed entry '{}' in [{}] of dictionary {}",
+                fields.join(" | "),
+                key,
+                name
+            )
+        });
+        values.push(value);
+        weights.push(weight);
+    }
+    let index = WeightedIndex::new(&weights)
+        .unwrap_or_else(|_| panic!("Section [{}] of dictionary {} is empty", key, name));
+    Weighted { values, index }
+}
+
+fn single(fields: &[String]) -> Option<String> {
+    fields.first().cloned()
+}
+
+impl Dictionary {
+    /// Dictionary from `{dir}/{locale}.dict` when present, the embedded one
+    /// otherwise.
+    pub fn for_locale(locale: &str, dir: Option<&str>) -> Dictionary {
+        if let Some(path) = dir.map(|dir| Path::new(dir).join(format!("{}.dict", locale))) {
+            if path.exists() {
+                let content = fs::read_to_string(&path).expect("Failed to read dictionary file");
+                let dictionary = Dictionary::parse(&path.to_string_lossy(), &content);
+                assert_eq!(
+                    dictionary.locale,
+                    locale,
+                    "Dictionary {} is for another locale",
+                    path.display()
+                );
+                return dictionary;
+            }
+        }
+        let (_, content) = EMBEDDED
+            .iter()
+            .find(|(name, _)| *name == locale)
+            .unwrap_or_else(|| panic!("No dictionary for locale '{}'", locale));
+        Dictionary::parse(locale, content)
+    }
+
+    pub fn parse(name: &str, content: &str) -> Dictionary {
+        let mut header: HashMap<String, String> = HashMap::new();
+        let mut sections: HashMap<String, Entries> = HashMap::new();
+        let mut current: Option<String> = None;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(key) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.entry(key.trim().to_string()).or_default();
+                current = Some(key.trim().to_string());
+                continue;
+            }
+            let (value, weight) = match line.rsplit_once('=') {
+                Some((value, weight)) => (value.trim(), weight.trim()),
+                None => (line, "1"),
+            };
+            match &current {
+                None => {
+                    header.insert(value.to_string(), weight.to_string());
+                }
+                Some(key) => {
+                    let weight = weight.parse().unwrap_or_else(|_| {
+                        panic!("Invalid weight in '{}' of dictionary {}", line, name)
+                    });
+                    let fields = value
+                        .split('|')
+                        .map(|f| text_column(f, MAX_VALUE_LEN))
+                        .collect();
+                    sections.get_mut(key).unwrap().push((fields, weight));
+                }
+            }
+        }
+
+        let header_value = |key: &str| {
+            header
+                .get(key)
+                .cloned()
+                .unwrap_or_else(|| panic!("Dictionary {} has no '{}'", name, key))
+        };
+        let phone_country_code = header_value("phone_country_code");
+        Dictionary {
+            locale: header_value("locale"),
+            country: header_value("country"),
+            phone_country_code: phone_country_code
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid phone_country_code in dictionary {}", name)),
+            mobile_prefixes: section(name, &mut sections, "mobile_prefixes", |f| {
+                single(f).filter(|p| p.chars().all(|c| c.is_ascii_digit()))
+            }),
+            male_first_names: section(name, &mut sections, "male_first_names", single),
+            female_first_names: section(name, &mut sections, "female_first_names", single),
+            surnames: section(name, &mut sections, "surnames", |f| {
+                let male = f.first()?.clone();
+                let female = f.get(1).unwrap_or(&male).clone();
+                Some((male, female))
+            }),
+            company_words: section(name, &mut sections, "company_words", single),
+            legal_forms: section(name, &mut sections, "legal_forms", single),
+            email_domains: section(name, &mut sections, "email_domains", single),
+            company_domain: section(name, &mut sections, "company_domain", single),
+            municipalities: section(name, &mut sections, "municipalities", |f| {
+                Some(Municipality {
+                    city: f.first()?.clone(),
+                    district: f.get(1)?.clone(),
+                    postal_code: f.get(2)?.parse().ok().filter(|c| (1..=99999).contains(c))?,
+                })
+            }),
+            streets: section(name, &mut sections, "streets", single),
+        }
+    }
+
+    /// First name and surname of the same gender.
+    pub fn person_name(&self) -> String {
+        let (male, female) = self.surnames.pick();
+        if Boolean(50).fake() {
+            format!("{} {}", self.male_first_names.pick(), male)
+        } else {
+            format!("{} {}", self.female_first_names.pick(), female)
+        }
+    }
+
+    pub fn company_name(&self) -> String {
+        let name = match (0..3).fake::<u8>() {
+            0 => format!("{} {}", self.company_words.pick(), self.surnames.pick().0),
+            1 => format!(
+                "{} {}",
+                self.company_words.pick(),
+                self.municipalities.pick().city
+            ),
+            _ => format!("{} & {}", self.surnames.pick().0, self.surnames.pick().0),
+        };
+        format!("{} {}", name, self.legal_forms.pick())
+    }
+
+    /// Free mail provider of the locale.
+    pub fn email_domain(&self) -> &str {
+        self.email_domains.pick()
+    }
+
+    /// Top level domain of company websites.
+    pub fn company_domain(&self) -> &str {
+        self.company_domain.pick()
+    }
+
+    /// Domain label made of a company name, the trailing legal form left out.
+    pub fn company_label(&self, name: &str) -> String {
+        let words: Vec<&str> = name.split_whitespace().collect();
+        let form = self
+            .legal_forms
+            .values
+            .iter()
+            .find(|f| name.ends_with(&f[..]));
+        let kept = words.len() - form.map_or(0, |f| f.split_whitespace().count());
+        let domain: Vec<String> = words[..kept.max(1)]
+            .iter()
+            .map(|part| email_part(part))
+            .filter(|part| !part.is_empty())
+            .collect();
+        domain.join("-")
+    }
+
+    /// Mobile number of the locale's country.
+    pub fn mobile_national_number(&self) -> u64 {
+        let prefix = self.mobile_prefixes.pick();
+        let (min, max) = super::PhoneNumber::national_length(self.phone_country_code);
+        let digits = (prefix.len() + 7).clamp(min, max);
+        let rest = digits.saturating_sub(prefix.len()) as u32;
+        let prefix: u64 = prefix.parse().unwrap();
+        prefix * 10u64.pow(rest) + (0..10u64.pow(rest)).fake::<u64>()
+    }
+
+    /// Street address whose city, district and postal code belong together.
+    pub fn postal_address(&self) -> PostalAddress {
+        let municipality = self.municipalities.pick();
+        PostalAddress {
+            city: municipality.city.clone(),
+            district: municipality.district.clone(),
+            street: self.streets.pick().clone(),
+            house_number: (1..3000).fake(),
+            postal_code: municipality.postal_code,
+        }
+    }
+}
+
+/// Lower case ASCII form of a name, as used in e-mail addresses.
+pub fn ascii_fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.to_lowercase().chars() {
+        match c {
+            'á' | 'ä' | 'à' | 'â' => folded.push('a'),
+            'č' | 'ç' => folded.push('c'),
+            'ď' => folded.push('d'),
+            'é' | 'ě' | 'è' | 'ê' | 'ë' => folded.push('e'),
+            'í' | 'î' | 'ï' => folded.push('i'),
+            'ľ' | 'ĺ' => folded.push('l'),
+            'ň' | 'ñ' => folded.push('n'),
+            'ó' | 'ô' | 'ö' => folded.push('o'),
+            'ř' | 'ŕ' => folded.push('r'),
+            'š' => folded.push('s'),
+            'ß' => folded.push_str("ss"),
+            'ť' => folded.push('t'),
+            'ú' | 'ů' | 'ü' | 'û' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'ž' => folded.push('z'),
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Folded name with everything but letters and digits left out.
+pub fn email_part(text: &str) -> String {
+    ascii_fold(text).replace(|c: char| !c.is_ascii_alphanumeric(), "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::generators::gen_address;
+    use crate::entities::CommaDelimited;
+
+    #[test]
+    fn addresses_of_every_embedded_dictionary_are_storable() {
+        for (locale, _) in EMBEDDED.iter() {
+            let dictionary = Dictionary::for_locale(locale, None);
+            for aid in 1..=250_000 {
+                let address = gen_address(aid, 1, &dictionary);
+                assert!(address.house_number > 0, "{:?}", address);
+                assert!((1..=99999).contains(&address.zip_code), "{:?}", address);
+                let texts = [
+                    &address.city,
+                    &address.street_name,
+                    address.district.as_ref().unwrap(),
+                ];
+                for text in texts {
+                    assert!(!text.is_empty(), "{:?}", address);
+                    assert!(text.chars().count() <= 100, "{:?}", address);
+                    assert_eq!(text, &text_column(text, 100), "{:?}", address);
+                }
+                assert_eq!(address.to_csv().split(',').count(), 7, "{:?}", address);
+            }
+        }
+    }
+}
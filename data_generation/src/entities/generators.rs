@@ -1,25 +1,116 @@
+use super::credentials::Credentials;
+use super::dictionary::Dictionary;
+use super::identity::Identities;
+use super::traffic::CallOutcome;
 use super::*;
+use crate::config::{CountRange, Scenario, Segment};
+use chrono::{DateTime, Duration, Utc};
 use fake::{
     faker::{self},
     Fake, Faker,
 };
 use rust_decimal::Decimal;
 
-pub fn gen_contract(cid: u32, vs: i32) -> Contract {
+/// Random date between `start` and `end` with minute precision, `start` when
+/// the range is empty.
+pub fn date_between(start: DateTime<Utc>, end: DateTime<Utc>) -> DateTime<Utc> {
+    use fake::faker::chrono::en::DateTimeBetween;
+    if end - start < Duration::minutes(1) {
+        return start;
+    }
+    DateTimeBetween(start, end).fake()
+}
+
+pub fn date_within(lifespan: &Lifespan) -> DateTime<Utc> {
+    date_between(lifespan.start, lifespan.until())
+}
+
+/// Lifespan of a child entity created during `parent`'s lifespan. It ends with
+/// the parent or, with `churn_percent` chance, on its own before that.
+pub fn gen_lifespan(parent: &Lifespan, churn_percent: u8) -> Lifespan {
+    gen_lifespan_from(date_within(parent), parent, churn_percent)
+}
+
+/// Lifespan of a child entity created at `start` during `parent`'s lifespan,
+/// ending like in `gen_lifespan`.
+pub fn gen_lifespan_from(start: DateTime<Utc>, parent: &Lifespan, churn_percent: u8) -> Lifespan {
     use faker::boolean::en::Boolean;
-    use faker::company::en::CompanyName;
-    use faker::internet::en::FreeEmail;
-    use faker::name::en::Name;
-    use faker::number::en::NumberWithFormat;
+    let churned: bool = Boolean(churn_percent).fake();
+    let end = if churned {
+        Some(date_between(start, parent.until()))
+    } else {
+        parent.end
+    };
+    Lifespan { start, end }
+}
+
+/// Random count within the range.
+pub fn gen_count(range: &CountRange) -> u32 {
+    (range.min..=range.max).fake()
+}
+
+/// Random channel limit within the range, zero meaning unlimited.
+pub fn gen_channel_limit(range: &CountRange) -> Option<u32> {
+    Some(gen_count(range)).filter(|limit| *limit > 0)
+}
 
-    let is_company: bool = Boolean(25).fake();
+/// Random valid number of the country's numbering plan.
+pub fn gen_phone_number(pcc: u16) -> PhoneNumber {
+    let (min, max) = PhoneNumber::national_length(pcc);
+    let digits = if min == max {
+        min
+    } else {
+        (min.max(8)..=max.min(11)).fake::<usize>()
+    };
+    let low = 10u64.pow(digits as u32 - 1);
+    PhoneNumber::new(pcc, (low..low * 10).fake::<u64>()).unwrap()
+}
+
+/// Company identification and VAT numbers as issued in the dictionary's
+/// country. Slovak IČO follow the Czech scheme, German companies only have a
+/// VAT number here and other countries neither.
+fn gen_company_ids(dictionary: &Dictionary) -> (Option<i32>, Option<String>) {
+    match &dictionary.country[..] {
+        "CZ" => {
+            let ico = cs_cz::ico();
+            (Some(ico as i32), Some(cs_cz::dic(ico)))
+        }
+        "SK" => {
+            // IČ DPH is a ten digit multiple of 11.
+            let vat = (1_000_000_000u64 / 11 + 1..9_999_999_999 / 11).fake::<u64>() * 11;
+            (Some(cs_cz::ico() as i32), Some(format!("SK{}", vat)))
+        }
+        "DE" => (
+            None,
+            Some(format!("DE{}", (100_000_000..999_999_999).fake::<u32>())),
+        ),
+        _ => (None, None),
+    }
+}
+
+pub fn gen_contract(
+    cid: u32,
+    vs: i32,
+    churn_percent: u8,
+    dictionary: &Dictionary,
+    segment: &Segment,
+    identities: &mut Identities,
+) -> Contract {
+    use faker::boolean::en::Boolean;
+
+    let is_company = segment.company;
     let has_bonus: bool = Boolean(25).fake();
     // let is_deleted: bool = Boolean(10).fake();
     let has_limit: bool = Boolean(25).fake();
     let name: String = if is_company {
-        Name().fake()
+        dictionary.company_name()
     } else {
-        CompanyName().fake()
+        dictionary.person_name()
+    };
+    let email = if is_company {
+        identities.company_email(&name, dictionary)
+    } else {
+        identities.person_email(&name, dictionary)
     };
     let bonus: Option<Decimal> = if has_bonus {
         Some(Decimal::from((50..500).fake::<u32>()))
@@ -31,180 +122,482 @@ pub fn gen_contract(cid: u32, vs: i32) -> Contract {
     } else {
         None
     };
-    let id: Option<i32> = if is_company {
-        Some((111111..9999999).fake::<i32>())
+    let (id, vat_id) = if is_company {
+        gen_company_ids(dictionary)
     } else {
-        None
-    };
-    let vat_id: Option<String> = if is_company {
-        Some(format!("CZ{}", id.unwrap()))
-    } else {
-        None
+        (None, None)
     };
 
-    Contract::new(
+    let lifespan = gen_lifespan(
+        &Lifespan {
+            start: Lifespan::epoch(),
+            end: None,
+        },
+        churn_percent,
+    );
+
+    let mut contract = Contract::new(
         cid,
         name,
         vs,
-        FreeEmail().fake(),
-        NumberWithFormat("+420 6## ### ###").fake(),
+        email,
+        PhoneNumber::new(
+            dictionary.phone_country_code,
+            dictionary.mobile_national_number(),
+        )
+        .unwrap(),
         bonus,
         limit,
         id,
         vat_id,
-    )
+    );
+    contract.created_at = Some(lifespan.start.to_rfc3339());
+    contract.deleted_at = lifespan.end.map(|d| d.to_rfc3339());
+    contract.locale = dictionary.locale.clone();
+    contract.segment = segment.name.clone();
+    contract.channel_limit = gen_channel_limit(&segment.contract_channels);
+    contract
 }
 
-pub fn gen_address(aid: u32, contract_id: u32) -> Address {
-    use fake::faker::address::en::{BuildingNumber, CityName, StreetName, ZipCode};
+pub fn gen_address(aid: u32, contract_id: u32, dictionary: &Dictionary) -> Address {
+    let address = dictionary.postal_address();
     Address::new(
         aid,
-        CityName().fake(),
-        None,
-        StreetName().fake(),
-        BuildingNumber().fake::<String>().parse::<i32>().unwrap(),
-        ZipCode().fake::<String>().parse::<i32>().unwrap(),
+        address.city,
+        Some(address.district),
+        address.street,
+        address.house_number,
+        address.postal_code,
         contract_id,
     )
 }
 
+/// Access level and lifespan of the `idx`-th participant of a contract. The
+/// first one owns the contract for its whole lifespan, the others come and go
+/// as admins with the segment's `admin_percent` chance, as users otherwise.
+pub fn gen_access_level(
+    idx: u32,
+    contract: &Lifespan,
+    segment: &Segment,
+    churn_percent: u8,
+) -> (u8, Lifespan) {
+    use faker::boolean::en::Boolean;
+    if idx == 0 {
+        return (ACCESS_LEVEL_OWNER, *contract);
+    }
+    let level = if Boolean(segment.admin_percent).fake() {
+        ACCESS_LEVEL_ADMIN
+    } else {
+        ACCESS_LEVEL_USER
+    };
+    (level, gen_lifespan(contract, churn_percent))
+}
+
 pub fn gen_participant(
     pid: u32,
     contract_id: u32,
-    f: &fake::StringFaker<std::ops::Range<usize>>,
+    access_level: u8,
+    lifespan: &Lifespan,
+    dictionary: &Dictionary,
+    identities: &mut Identities,
+    credentials: &mut Credentials,
 ) -> Participant {
     use faker::boolean::en::Boolean;
-    use faker::name::en::FirstName;
     let has_limit: bool = Boolean(25).fake();
+    let name = dictionary.person_name();
+    let login = identities.login(&name);
 
     Participant::new(
         pid,
-        FirstName().fake(),
-        (1..=3).fake::<u8>(),
+        login.clone(),
+        name,
+        access_level,
         contract_id,
-        f.fake::<String>(),
+        credentials.participant_password(pid, &login),
         if has_limit {
             Some(Decimal::from((10..100).fake::<u32>()))
         } else {
             None
         },
-        None,
-        None,
+        Some(lifespan.start.to_rfc3339()),
+        lifespan.end.map(|d| d.to_rfc3339()),
     )
 }
 
+/// Permission rows matching each participant's access level.
+pub fn gen_permissions(participants: &[Participant]) -> Vec<Permission> {
+    participants
+        .iter()
+        .flat_map(|p| {
+            access_level_permissions(p.access_level)
+                .iter()
+                .map(move |permission| Permission {
+                    participant_id: p.participant_id.unwrap(),
+                    permission: permission.to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Numbers without a `lifespan` were never activated and wait for a request
+/// to be resolved. Released numbers get their quarantine and final state from
+/// the number lifecycle simulation, which may also swap the number for a
+/// reused one, so the SIP password is set afterwards by `set_sip_password`.
 pub fn gen_voip_number(
     nid: u32,
     pid: Option<u32>,
-    f: &fake::StringFaker<std::ops::Range<usize>>,
+    lifespan: Option<&Lifespan>,
+    number: PhoneNumber,
 ) -> VoipNumber {
-    use chrono::prelude::*;
     use fake::faker::boolean::en::Boolean;
-    use fake::faker::chrono::en::DateTimeBetween;
-    use fake::faker::number::en::NumberWithFormat;
-
-    let end_dt: DateTime<Utc> = Utc::now();
-    let start_dt: DateTime<Utc> = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
-    let is_in_quarantine = Boolean(20).fake();
 
     VoipNumber::new(
         Some(nid),
-        420,
-        NumberWithFormat("5########")
-            .fake::<String>()
-            .parse::<u32>()
-            .unwrap(),
+        number,
         pid,
-        f.fake::<String>(),
-        (1..4).fake::<u8>(),
-        Boolean(35).fake(),
-        if is_in_quarantine {
-            Some(DateTimeBetween(start_dt, end_dt).fake())
+        String::new(),
+        if lifespan.is_some() {
+            NUMBER_STATE_ACTIVE
         } else {
-            None
+            NUMBER_STATE_REQUESTED
         },
-        DateTimeBetween(start_dt, end_dt).fake(),
+        Boolean(35).fake(),
         None,
+        lifespan.map(|l| l.start.to_rfc3339()),
+        lifespan.and_then(|l| l.end).map(|d| d.to_rfc3339()),
     )
 }
 
-pub fn gen_price_list(id: u32, pcc: u16, price: u16, t1: u8, t2: u8) -> PriceList {
-    PriceList {
-        phone_country_code: pcc,
-        price_list_id: Some(id),
-        price_per_second: price,
-        tariffication_first: t1,
-        tariffication_second: t2,
+/// Destination prefix with its price per second in hundredths of CZK and
+/// first/next tariffication blocks.
+type Destination = (&'static str, u16, u8, u8);
+
+/// Premium rate prefixes among the `DESTINATIONS`. Plans never discount them
+/// nor count them against included minutes.
+pub const PREMIUM_PREFIXES: [&str; 5] = ["42090", "421900", "48700", "49900", "43900"];
+
+/// Destination prefixes rated by the generated price lists. Country wide
+/// prefixes catch fixed lines, longer ones mobile and premium numbers.
+const DESTINATIONS: [(u16, &[Destination]); 5] = [
+    (
+        420,
+        &[
+            ("420", 10, 1, 1),
+            ("4206", 15, 1, 1),
+            ("4207", 15, 1, 1),
+            ("42090", 300, 60, 60),
+        ],
+    ),
+    (
+        421,
+        &[
+            ("421", 15, 60, 1),
+            ("4219", 25, 60, 1),
+            ("421900", 350, 60, 60),
+        ],
+    ),
+    (
+        48,
+        &[
+            ("48", 20, 60, 10),
+            ("485", 30, 60, 10),
+            ("486", 30, 60, 10),
+            ("487", 30, 60, 10),
+            ("488", 30, 60, 10),
+            ("48700", 400, 60, 60),
+        ],
+    ),
+    (
+        49,
+        &[
+            ("49", 30, 60, 20),
+            ("4915", 45, 60, 20),
+            ("4916", 45, 60, 20),
+            ("4917", 45, 60, 20),
+            ("49900", 500, 60, 60),
+        ],
+    ),
+    (
+        43,
+        &[
+            ("43", 35, 60, 20),
+            ("4366", 50, 60, 20),
+            ("43900", 500, 60, 60),
+        ],
+    ),
+];
+
+/// Country code with its prefix, price of an SMS sent there and of a megabyte
+/// of data used in its networks, both in hundredths of CZK.
+const USAGE_PRICES: [(u16, &str, u16, u16); 5] = [
+    (420, "420", 150, 10),
+    (421, "421", 300, 20),
+    (48, "48", 300, 20),
+    (49, "49", 350, 25),
+    (43, "43", 350, 25),
+];
+
+/// Country codes the generated price lists cover.
+pub fn price_list_countries() -> Vec<u16> {
+    DESTINATIONS.iter().map(|(pcc, _)| *pcc).collect()
+}
+
+/// Price lists for every voice destination prefix and for SMS and data per
+/// country. Each prefix gets up to three consecutive validity periods since
+/// the epoch, the last one open ended, with the price drifting by up to a
+/// fifth on every change.
+pub fn gen_price_lists(last_id: u32) -> Vec<PriceList> {
+    let voice = DESTINATIONS.iter().flat_map(|(pcc, prefixes)| {
+        prefixes
+            .iter()
+            .map(move |(prefix, price, t1, t2)| (SERVICE_VOICE, *pcc, *prefix, *price, (*t1, *t2)))
+    });
+    let usage = USAGE_PRICES.iter().flat_map(|(pcc, prefix, sms, data)| {
+        vec![
+            (SERVICE_SMS, *pcc, *prefix, *sms, (1, 1)),
+            (SERVICE_DATA, *pcc, *prefix, *data, (1, 1)),
+        ]
+    });
+
+    let mut price_lists: Vec<PriceList> = vec![];
+    for (service, pcc, prefix, price, tariffication) in voice.chain(usage) {
+        let mut changes: Vec<DateTime<Utc>> = (0..(0..3).fake::<usize>())
+            .map(|_| date_between(Lifespan::epoch(), Utc::now()))
+            .collect();
+        changes.sort();
+        let mut from = Lifespan::epoch();
+        let mut price = u32::from(price);
+        for to in changes.into_iter().map(Some).chain(std::iter::once(None)) {
+            let mut list = PriceList::new(
+                Some(last_id + price_lists.len() as u32 + 1),
+                pcc,
+                prefix,
+                price.min(u32::from(u16::MAX)) as u16,
+                tariffication,
+                from.to_rfc3339(),
+                to.map(|d| d.to_rfc3339()),
+            );
+            list.service = service;
+            price_lists.push(list);
+            price = (price * (80..=120).fake::<u32>() / 100).max(1);
+            if let Some(to) = to {
+                from = to;
+            }
+        }
     }
+    price_lists
+}
+
+/// Plan name, monthly fee in hundredths of CZK, included minutes and prices
+/// per second overriding price list prefixes.
+type PlanTemplate = (&'static str, i64, u32, &'static [(&'static str, u16)]);
+
+/// Plans offered to contracts. Higher tiers include more minutes, the business
+/// plan makes Slovak calls cheaper and the unlimited one domestic calls free,
+/// premium numbers keep their price list price on every plan, see `Tariff`.
+const PLANS: [PlanTemplate; 4] = [
+    ("Basic", 0, 0, &[]),
+    ("Standard", 19900, 100, &[]),
+    ("Business", 49900, 500, &[("421", 8), ("4219", 15)]),
+    (
+        "Unlimited",
+        89900,
+        3000,
+        &[("420", 0), ("4206", 0), ("4207", 0)],
+    ),
+];
+
+/// Plan catalogue with the destination overrides of each plan. Plans are
+/// billed through consecutive invoice items from `first_item_id`.
+pub fn gen_plans(last_id: u32, first_item_id: u32) -> (Vec<Plan>, Vec<PlanRate>) {
+    let mut plans: Vec<Plan> = vec![];
+    let mut rates: Vec<PlanRate> = vec![];
+    for (idx, (name, fee, minutes, overrides)) in PLANS.iter().enumerate() {
+        let plan_id = last_id + idx as u32 + 1;
+        plans.push(Plan {
+            plan_id,
+            name: name.to_string(),
+            monthly_fee: Decimal::new(*fee, 2),
+            included_minutes: *minutes,
+            invoice_item_id: first_item_id + idx as u32,
+        });
+        rates.extend(overrides.iter().map(|(prefix, price)| PlanRate {
+            plan_id,
+            prefix: prefix.to_string(),
+            price_per_second: *price,
+        }));
+    }
+    (plans, rates)
+}
+
+/// Plans the contract was subscribed to over its lifespan. It starts on a
+/// random plan and, with `change_percent` chance each time, moves to another
+/// one, at most three times.
+pub fn gen_subscriptions(
+    last_id: u32,
+    contract: &Contract,
+    plans: &[Plan],
+    change_percent: u8,
+) -> Vec<Subscription> {
+    use fake::faker::boolean::en::Boolean;
+
+    let lifespan = contract.lifespan();
+    let mut subscriptions: Vec<Subscription> = vec![];
+    let mut from = lifespan.start;
+    let mut plan = (0..plans.len()).fake::<usize>();
+    loop {
+        let changed = subscriptions.len() < 3 && Boolean(change_percent).fake();
+        let to = if changed {
+            Some(date_between(from, lifespan.until()))
+        } else {
+            lifespan.end
+        };
+        subscriptions.push(Subscription {
+            subscription_id: last_id + subscriptions.len() as u32 + 1,
+            contract_id: contract.contract_id.unwrap(),
+            plan_id: plans[plan].plan_id,
+            valid_from: from.to_rfc3339(),
+            valid_to: to.map(|d| d.to_rfc3339()),
+        });
+        match to {
+            Some(to) if changed => {
+                from = to;
+                plan = (plan + (1..plans.len()).fake::<usize>()) % plans.len();
+            }
+            _ => break,
+        }
+    }
+    subscriptions
 }
 
 pub fn gen_cdr(
     id: u32,
     pcc: u16,
-    price_list_id: u32,
-    number_str: String,
+    number: PhoneNumber,
     number_id: u32,
+    call_date: DateTime<Utc>,
+    outcome: CallOutcome,
 ) -> CallDetailRecord {
-    use chrono::prelude::*;
     use fake::faker::boolean::en::Boolean;
-    use fake::faker::chrono::en::DateTimeBetween;
-    use fake::faker::number::en::NumberWithFormat;
-
-    let end_dt: DateTime<Utc> = Utc::now();
-    let start_dt: DateTime<Utc> = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
 
-    let dispositions: [String; 3] = [
-        "HANGUP".to_string(),
-        "ANSWER".to_string(),
-        "ERROR".to_string(),
-    ];
-    let disposition_pick = (0..3).fake::<usize>();
     let is_incoming = Boolean(50).fake();
-    let mut num1: String;
-    let mut num2: String;
-    if is_incoming {
-        num1 = String::from(format!("+{}", pcc));
-        num1 += &NumberWithFormat("#########").fake::<String>();
-        num2 = number_str;
+    let (source_num, destination_num) = if is_incoming {
+        (gen_phone_number(pcc), number)
     } else {
-        num1 = number_str;
-        num2 = String::from(format!("+{}", pcc));
-        num2 += &NumberWithFormat("#########").fake::<String>();
+        (number, gen_phone_number(pcc))
+    };
+
+    CallDetailRecord {
+        call_id: Some(id),
+        disposition: outcome.disposition.to_string(),
+        source_num,
+        destination_num,
+        length: outcome.ring_duration + outcome.billsec,
+        ring_duration: outcome.ring_duration,
+        billsec: outcome.billsec,
+        failure_reason: None,
+        call_date: call_date.to_rfc3339(),
+        number_id,
+        incoming_outgoing: is_incoming,
+        price_list_id: None,
+        linked_id: id,
+        leg: 1,
+        leg_type: None,
+        price: None,
+        included_seconds: 0,
     }
+}
+
+pub fn gen_sms(
+    id: u32,
+    pcc: u16,
+    number: PhoneNumber,
+    number_id: u32,
+    sent_at: DateTime<Utc>,
+) -> SmsRecord {
+    use fake::faker::boolean::en::Boolean;
 
-    CallDetailRecord::new(
-        Some(id),
-        dispositions[disposition_pick].clone(),
-        num1,
-        num2,
-        (1..300).fake::<u16>(),
-        DateTimeBetween(start_dt, end_dt).fake(),
+    let incoming = Boolean(50).fake();
+    let (source_num, destination_num) = if incoming {
+        (gen_phone_number(pcc), number)
+    } else {
+        (number, gen_phone_number(pcc))
+    };
+    // Most texts fit one message, long ones get split.
+    let parts = match (0..100).fake::<u8>() {
+        0..=84 => 1,
+        85..=96 => 2,
+        _ => (3..=6).fake(),
+    };
+    SmsRecord {
+        sms_id: id,
         number_id,
-        is_incoming,
-        Some(price_list_id),
-    )
+        incoming,
+        source_num,
+        destination_num,
+        sent_at: sent_at.to_rfc3339(),
+        parts,
+        price_list_id: None,
+        price: None,
+    }
+}
+
+/// Data session of a number in the network of `pcc`. Durations and volumes
+/// are spread over orders of magnitude, from a quick sync to an hours long
+/// stream, with uploads a fraction of downloads.
+pub fn gen_data_session(
+    id: u32,
+    pcc: u16,
+    number_id: u32,
+    started_at: DateTime<Utc>,
+) -> DataSession {
+    let scale = (0.0..1.0).fake::<f64>();
+    let duration = 10f64 * 720f64.powf(scale);
+    let bytes_down = 20_000f64 * 25_000f64.powf(scale * (0.7..1.0).fake::<f64>());
+    let bytes_up = bytes_down * (0.02..0.3).fake::<f64>();
+    DataSession {
+        session_id: id,
+        number_id,
+        phone_country_code: pcc,
+        started_at: started_at.to_rfc3339(),
+        duration: duration as u32,
+        bytes_up: bytes_up as u64,
+        bytes_down: bytes_down as u64,
+        price_list_id: None,
+        price: None,
+    }
 }
 
 pub fn gen_invoice_item(item_id: u32, item_name: String) -> InvoiceItem {
     InvoiceItem::new(Some(item_id), item_name, Faker.fake::<f32>())
 }
 
-pub fn gen_invoice(invoice_number: u64, amount: f32, contract_id: u32) -> Invoice {
-    use chrono::prelude::*;
-    use fake::faker::chrono::en::DateTimeBetween;
+pub fn gen_invoice(
+    invoice_number: u64,
+    amount: f32,
+    contract_id: u32,
+    lifespan: &Lifespan,
+) -> Invoice {
+    gen_invoice_at(invoice_number, amount, contract_id, date_within(lifespan))
+}
+
+/// Invoice issued at `created_at`, due two weeks later and mostly paid.
+pub fn gen_invoice_at(
+    invoice_number: u64,
+    amount: f32,
+    contract_id: u32,
+    created_at: DateTime<Utc>,
+) -> Invoice {
     use faker::boolean::en::Boolean;
 
-    let end_dt: DateTime<Utc> = Utc::now();
-    let start_dt: DateTime<Utc> = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let maturity = created_at + Duration::days(14);
     let mut paid = None;
     let is_paid = Boolean(80).fake();
     if is_paid {
-        paid = Some(DateTimeBetween(start_dt, end_dt).fake())
+        let latest = (maturity + Duration::days(30)).min(Utc::now());
+        paid = Some(date_between(created_at, latest).to_rfc3339())
     }
 
-    let created_at: chrono::DateTime<Utc> = DateTimeBetween(start_dt, end_dt).fake();
-    let maturity = created_at + chrono::Duration::days(14);
-
     Invoice::new(
         invoice_number,
         amount,
@@ -217,11 +610,142 @@ pub fn gen_invoice(invoice_number: u64, amount: f32, contract_id: u32) -> Invoic
     )
 }
 
-pub fn gen_number_request(num_id: u32, part_id: u32) -> NumberRequest {
-    use chrono::prelude::*;
-    use fake::faker::chrono::en::DateTimeBetween;
-    let end_dt: DateTime<Utc> = Utc::now();
-    let start_dt: DateTime<Utc> = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
-    let requested = DateTimeBetween(start_dt, end_dt).fake();
-    NumberRequest::new(part_id, num_id, requested)
+/// Number request of a participant with its outcome drawn from the scenario
+/// weights. Approvals and rejections come within the expiry period while the
+/// participant exists, expired requests are decided when the period ends.
+/// Outcomes the participant's lifespan rules out fall back to a possible one,
+/// e.g. a pending request of a participant that already left is rejected.
+pub fn gen_number_request(
+    num_id: u32,
+    part_id: u32,
+    lifespan: &Lifespan,
+    scenario: &Scenario,
+) -> NumberRequest {
+    use rand::distributions::WeightedIndex;
+    use rand_distr::Distribution;
+
+    const OUTCOMES: [&str; 4] = [
+        REQUEST_PENDING,
+        REQUEST_APPROVED,
+        REQUEST_REJECTED,
+        REQUEST_EXPIRED,
+    ];
+    let now = Utc::now();
+    let expiry = Duration::days(i64::from(scenario.number_request_expiry_days));
+    let weights = WeightedIndex::new(&scenario.number_request_outcome_weights)
+        .expect("Invalid number request outcome weights");
+    let mut status = OUTCOMES[weights.sample(&mut rand::thread_rng())];
+
+    // Only participants still around have open requests, and only requests
+    // older than the expiry period can have expired.
+    let pending_since = lifespan.start.max(now - expiry);
+    if status == REQUEST_PENDING && lifespan.end.is_some() {
+        status = REQUEST_REJECTED;
+    }
+    if status == REQUEST_EXPIRED && lifespan.start + expiry > now {
+        status = if lifespan.end.is_some() {
+            REQUEST_REJECTED
+        } else {
+            REQUEST_PENDING
+        };
+    }
+
+    let (requested, decided) = match status {
+        REQUEST_PENDING => (date_between(pending_since, now), None),
+        REQUEST_EXPIRED => {
+            let requested = date_between(lifespan.start, (now - expiry).min(lifespan.until()));
+            (requested, Some(requested + expiry))
+        }
+        _ => {
+            let requested = date_within(lifespan);
+            let latest = (requested + expiry).min(lifespan.until());
+            (requested, Some(date_between(requested, latest)))
+        }
+    };
+    NumberRequest::new(
+        part_id,
+        num_id,
+        requested.to_rfc3339(),
+        status,
+        decided.map(|d| d.to_rfc3339()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Access levels of the participants of 1000 contracts of each default
+    /// segment, by contract.
+    fn access_levels() -> Vec<Vec<u8>> {
+        let contract = Lifespan {
+            start: Lifespan::epoch(),
+            end: None,
+        };
+        Scenario::default()
+            .segments
+            .iter()
+            .flat_map(|segment| {
+                (0..1000).map(move |_| {
+                    (0..gen_count(&segment.participants))
+                        .map(|idx| gen_access_level(idx, &contract, segment, 30).0)
+                        .collect()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_contract_has_one_owner_and_admins_only_among_others() {
+        let contracts = access_levels();
+        assert!(contracts.iter().flatten().any(|l| *l == ACCESS_LEVEL_ADMIN));
+        for levels in &contracts {
+            let owners = levels.iter().filter(|l| **l == ACCESS_LEVEL_OWNER).count();
+            assert_eq!(owners, 1, "{:?}", levels);
+            if levels.len() == 1 {
+                assert_eq!(levels[0], ACCESS_LEVEL_OWNER);
+            }
+        }
+    }
+
+    #[test]
+    fn permissions_match_access_levels() {
+        let participants: Vec<Participant> = access_levels()
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(idx, level)| {
+                let pid = idx as u32 + 1;
+                Participant::new(
+                    pid,
+                    format!("user{}", pid),
+                    format!("User {}", pid),
+                    *level,
+                    1,
+                    String::new(),
+                    None,
+                    None,
+                    None,
+                )
+            })
+            .collect();
+
+        let permissions = gen_permissions(&participants);
+        let mut granted: HashMap<u32, Vec<&str>> = HashMap::new();
+        for permission in &permissions {
+            granted
+                .entry(permission.participant_id)
+                .or_default()
+                .push(&permission.permission);
+        }
+        for participant in &participants {
+            assert_eq!(
+                granted[&participant.participant_id.unwrap()],
+                access_level_permissions(participant.access_level),
+                "{:?}",
+                participant
+            );
+        }
+    }
 }
@@ -0,0 +1,243 @@
+use super::dictionary::{email_part, parse_share, Dictionary};
+use fake::Fake;
+use rand::distributions::WeightedIndex;
+use rand::seq::SliceRandom;
+use rand_distr::Distribution;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Mail domain with its share of personal e-mail addresses, written as
+/// `{domain}:{weight}` in the scenario file.
+#[derive(Debug, Clone)]
+pub struct DomainShare {
+    pub domain: String,
+    pub weight: u32,
+}
+
+impl FromStr for DomainShare {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<DomainShare, String> {
+        let (domain, weight) = parse_share(value)?;
+        Ok(DomainShare {
+            domain: domain.to_lowercase(),
+            weight,
+        })
+    }
+}
+
+/// Hands out e-mail addresses and login handles derived from names, each one
+/// at most once per run. Values already stored in the database are reserved
+/// up front so new rows never collide with them.
+pub struct Identities {
+    emails: HashSet<String>,
+    logins: HashSet<String>,
+    /// Next numeric suffix to try for a base value.
+    suffixes: HashMap<String, u32>,
+    domains: Vec<String>,
+    domain_index: Option<WeightedIndex<u32>>,
+}
+
+impl Identities {
+    /// Personal addresses use `domains` when given, the dictionary's free mail
+    /// providers otherwise.
+    pub fn new(domains: &[DomainShare]) -> Identities {
+        let domain_index = if domains.is_empty() {
+            None
+        } else {
+            Some(
+                WeightedIndex::new(domains.iter().map(|d| d.weight))
+                    .expect("Invalid email domain weights"),
+            )
+        };
+        Identities {
+            emails: HashSet::new(),
+            logins: HashSet::new(),
+            suffixes: HashMap::new(),
+            domains: domains.iter().map(|d| d.domain.clone()).collect(),
+            domain_index,
+        }
+    }
+
+    /// Marks an address as taken, compared case insensitively.
+    pub fn reserve_email(&mut self, email: &str) {
+        self.emails.insert(email.to_lowercase());
+    }
+
+    pub fn reserve_login(&mut self, login: &str) {
+        self.logins.insert(login.to_lowercase());
+    }
+
+    /// Personal address like `jan.novak@seznam.cz`, falling back to other
+    /// arrangements of the name and then to a numbered one when taken.
+    pub fn person_email(&mut self, name: &str, dictionary: &Dictionary) -> String {
+        let domain = match &self.domain_index {
+            Some(index) => self.domains[index.sample(&mut rand::thread_rng())].clone(),
+            None => dictionary.email_domain().to_string(),
+        };
+        let (first, last) = name_parts(name);
+        let mut locals = [
+            format!("{}.{}", first, last),
+            format!("{}.{}", last, first),
+            format!("{}{}", initial(&first), last),
+            format!("{}{}", first, last),
+        ];
+        // Most people keep the plain form, the rest is spread over the others.
+        locals[1..].shuffle(&mut rand::thread_rng());
+        if (0..4).fake::<u8>() == 0 {
+            locals.swap(0, 1);
+        }
+        let candidates: Vec<String> = locals
+            .iter()
+            .map(|local| format!("{}@{}", local, domain))
+            .collect();
+        let numbered = format!("{}.{}{{}}@{}", first, last, domain);
+        unique(&mut self.emails, &mut self.suffixes, &candidates, &numbered)
+    }
+
+    /// Office address on the company's own domain. Namesake companies get a
+    /// numbered domain of their own.
+    pub fn company_email(&mut self, name: &str, dictionary: &Dictionary) -> String {
+        let label = dictionary.company_label(name);
+        let tld = dictionary.company_domain();
+        let candidates = [format!("info@{}.{}", label, tld)];
+        let numbered = format!("info@{}{{}}.{}", label, tld);
+        unique(&mut self.emails, &mut self.suffixes, &candidates, &numbered)
+    }
+
+    /// Login handle like `jnovak` or `jan.novak`.
+    pub fn login(&mut self, name: &str) -> String {
+        let (first, last) = name_parts(name);
+        let mut candidates = [
+            format!("{}{}", initial(&first), last),
+            format!("{}.{}", first, last),
+        ];
+        candidates.shuffle(&mut rand::thread_rng());
+        let numbered = format!("{}{}{{}}", initial(&first), last);
+        unique(&mut self.logins, &mut self.suffixes, &candidates, &numbered)
+    }
+}
+
+/// First and last word of a name folded to ASCII, `user` for a name without
+/// any usable characters.
+fn name_parts(name: &str) -> (String, String) {
+    let parts: Vec<String> = name
+        .split_whitespace()
+        .map(email_part)
+        .filter(|part| !part.is_empty())
+        .collect();
+    match (parts.first(), parts.last()) {
+        (Some(first), Some(last)) if parts.len() > 1 => (first.clone(), last.clone()),
+        (Some(only), _) => (only.clone(), String::from("user")),
+        _ => (String::from("user"), String::from("user")),
+    }
+}
+
+fn initial(part: &str) -> &str {
+    &part[..1]
+}
+
+/// First free candidate, otherwise `numbered` with its `{}` replaced by the
+/// next free number counting from 2.
+fn unique(
+    used: &mut HashSet<String>,
+    suffixes: &mut HashMap<String, u32>,
+    candidates: &[String],
+    numbered: &str,
+) -> String {
+    for candidate in candidates {
+        if used.insert(candidate.clone()) {
+            return candidate.clone();
+        }
+    }
+    let next = suffixes.entry(numbered.to_string()).or_insert(2);
+    loop {
+        let candidate = numbered.replace("{}", &next.to_string());
+        *next += 1;
+        if used.insert(candidate.clone()) {
+            return candidate;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identities() -> Identities {
+        Identities::new(&[DomainShare {
+            domain: String::from("example.cz"),
+            weight: 1,
+        }])
+    }
+
+    #[test]
+    fn namesakes_get_numbered_values() {
+        let dictionary = Dictionary::for_locale("cs_CZ", None);
+        let mut identities = identities();
+        let emails: HashSet<String> = (0..10)
+            .map(|_| identities.person_email("Jan Novák", &dictionary))
+            .collect();
+        let logins: HashSet<String> = (0..10).map(|_| identities.login("Jan Novák")).collect();
+
+        assert_eq!(emails.len(), 10);
+        for n in 2..=7 {
+            assert!(
+                emails.contains(&format!("jan.novak{}@example.cz", n)),
+                "{:?}",
+                emails
+            );
+        }
+        assert_eq!(logins.len(), 10);
+        assert!(logins.contains("jnovak") && logins.contains("jan.novak"));
+        for n in 2..=9 {
+            assert!(logins.contains(&format!("jnovak{}", n)), "{:?}", logins);
+        }
+    }
+
+    #[test]
+    fn reserved_values_are_never_handed_out() {
+        let dictionary = Dictionary::for_locale("cs_CZ", None);
+        let mut identities = identities();
+        let emails = [
+            "Jan.Novak@example.cz",
+            "novak.jan@example.cz",
+            "jan.novak3@example.cz",
+        ];
+        let logins = ["JNovak", "jnovak2"];
+        for email in emails {
+            identities.reserve_email(email);
+        }
+        for login in logins {
+            identities.reserve_login(login);
+        }
+
+        for _ in 0..20 {
+            let email = identities.person_email("Jan Novák", &dictionary);
+            assert!(
+                !emails.iter().any(|e| e.eq_ignore_ascii_case(&email)),
+                "{}",
+                email
+            );
+            let login = identities.login("Jan Novák");
+            assert!(
+                !logins.iter().any(|l| l.eq_ignore_ascii_case(&login)),
+                "{}",
+                login
+            );
+        }
+    }
+
+    #[test]
+    fn names_without_ascii_letters_fall_back_to_user() {
+        let dictionary = Dictionary::for_locale("cs_CZ", None);
+        let mut identities = identities();
+        let email = identities.person_email("李 小龍", &dictionary);
+        let login = identities.login("Владимир");
+
+        let forms = ["user.user", "uuser", "useruser"];
+        let local = email.strip_suffix("@example.cz").unwrap();
+        assert!(forms.contains(&local), "{}", email);
+        assert!(forms.contains(&login.as_str()), "{}", login);
+    }
+}
@@ -1,7 +1,46 @@
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
 use rust_decimal::Decimal;
 use std::fmt;
 
+pub mod credentials;
+pub mod cs_cz;
+pub mod dictionary;
 pub mod generators;
+pub mod identity;
+pub mod number_plan;
+pub mod phone_number;
+pub mod traffic;
+
+pub use phone_number::PhoneNumber;
+
+pub fn parse_date(date: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(date).expect("Generated dates are always RFC 3339")
+}
+
+/// Period during which an entity exists, open ended while it is not deleted.
+#[derive(Debug, Clone, Copy)]
+pub struct Lifespan {
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl Lifespan {
+    /// Everything generated lives between this date and now.
+    pub fn epoch() -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(0, 0, 0)
+    }
+
+    pub fn from_strings(start: &str, end: Option<&String>) -> Lifespan {
+        Lifespan {
+            start: parse_date(start).with_timezone(&Utc),
+            end: end.map(|e| parse_date(e).with_timezone(&Utc)),
+        }
+    }
+
+    pub fn until(&self) -> DateTime<Utc> {
+        self.end.unwrap_or_else(Utc::now)
+    }
+}
 
 pub trait CommaDelimited {
     fn to_csv(&self) -> String;
@@ -17,6 +56,18 @@ pub trait RecreatesForeignKeys {
     fn recreate_fk() -> Option<&'static str>;
 }
 
+/// Implemented by entities whose table is not part of the original schema
+/// and gets created by the generator on first run.
+pub trait CreatesTable {
+    fn create_table() -> &'static str;
+}
+
+/// Implemented by entities that store columns the original schema lacks,
+/// `;` separated statements adding them when missing.
+pub trait AltersTable {
+    fn alter_table() -> &'static str;
+}
+
 #[derive(Debug)]
 pub struct Contract {
     pub contract_id: Option<u32>,
@@ -28,8 +79,15 @@ pub struct Contract {
     pub deleted_at: Option<String>,
     pub notify_limit: Option<Decimal>,
     pub email: String,
-    pub phone_number: String,
+    pub phone_number: PhoneNumber,
     pub bonus_amount: Option<Decimal>,
+    /// Locale of the dictionary the contract's data was generated from.
+    pub locale: String,
+    /// Name of the customer segment the contract belongs to.
+    pub segment: String,
+    /// Simultaneous calls all participants can have together, unlimited
+    /// when `None`.
+    pub channel_limit: Option<u32>,
 }
 
 impl Contract {
@@ -38,7 +96,7 @@ impl Contract {
         cn: String,
         vs: i32,
         email: String,
-        pn: String,
+        pn: PhoneNumber,
         bonus: Option<Decimal>,
         notify: Option<Decimal>,
         id: Option<i32>,
@@ -56,8 +114,20 @@ impl Contract {
             notify_limit: notify,
             identification_number: id,
             vat_identification_number: vat_id,
+            locale: String::from("cs_CZ"),
+            segment: String::from("residential"),
+            channel_limit: None,
         }
     }
+
+    pub fn lifespan(&self) -> Lifespan {
+        Lifespan::from_strings(
+            self.created_at
+                .as_ref()
+                .expect("Contract without created_at"),
+            self.deleted_at.as_ref(),
+        )
+    }
 }
 
 impl fmt::Display for Contract {
@@ -84,7 +154,7 @@ impl fmt::Display for Contract {
 impl SqlInsert for Contract {
     fn insert_header() -> String {
         "contract(contract_id, contract_name, variable_symbol, identification_number, vat_identification_number, \
-            deleted_at, notify_limit, email, phone_number, bonus_amount)".to_string()
+            created_at, deleted_at, notify_limit, email, phone_number, bonus_amount, locale, segment, channel_limit)".to_string()
     }
 
     fn table_name() -> String {
@@ -95,7 +165,7 @@ impl SqlInsert for Contract {
 impl CommaDelimited for Contract {
     fn to_csv(&self) -> String {
         format!(
-            "{id},{name},{vs},{id_number},{vat_id},{del},{not},{email},{pn},{bonus}\n",
+            "{id},{name},{vs},{id_number},{vat_id},{cr},{del},{not},{email},{pn},{bonus},{locale},{segment},{channels}\n",
             id = if self.contract_id.is_some() {
                 self.contract_id.unwrap().to_string()
             } else {
@@ -112,6 +182,7 @@ impl CommaDelimited for Contract {
                 .vat_identification_number
                 .as_ref()
                 .unwrap_or(&"nul_val".to_string()),
+            cr = self.created_at.as_ref().unwrap_or(&"nul_val".to_string()),
             del = self.deleted_at.as_ref().unwrap_or(&"nul_val".to_string()),
             not = if self.notify_limit.is_some() {
                 self.notify_limit.unwrap().to_string()
@@ -119,16 +190,29 @@ impl CommaDelimited for Contract {
                 "nul_val".to_string()
             },
             email = self.email,
-            pn = self.phone_number,
+            pn = self.phone_number.international_format(),
             bonus = if self.bonus_amount.is_some() {
                 self.bonus_amount.unwrap().to_string()
             } else {
                 "nul_val".to_string()
             },
+            locale = self.locale,
+            segment = self.segment,
+            channels = self
+                .channel_limit
+                .map_or("nul_val".to_string(), |l| l.to_string()),
         )
     }
 }
 
+impl AltersTable for Contract {
+    fn alter_table() -> &'static str {
+        "alter table contract add column if not exists locale varchar(5); \
+    alter table contract add column if not exists segment varchar(20); \
+    alter table contract add column if not exists channel_limit integer"
+    }
+}
+
 impl RecreatesForeignKeys for Contract {
     fn drop_fk() -> std::option::Option<&'static str> {
         None
@@ -141,15 +225,43 @@ impl RecreatesForeignKeys for Contract {
 #[derive(Debug)]
 pub struct Address {
     address_id: Option<u32>,
-    city: String,
-    district: Option<String>,
-    street_name: String,
-    house_number: i32,
-    zip_code: i32,
-    contract_id: u32,
+    pub city: String,
+    pub district: Option<String>,
+    pub street_name: String,
+    pub house_number: i32,
+    pub zip_code: i32,
+    pub contract_id: u32,
+}
+
+/// Longest text the `address` columns hold.
+const ADDRESS_TEXT_LEN: usize = 100;
+
+/// Text safe to COPY into a `varchar(max_len)` column. Delimiters, escapes and
+/// control characters become spaces and the rest is cut to `max_len` chars.
+pub fn text_column(value: &str, max_len: usize) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c == ',' || c == '\\' || c.is_control() {
+                ' '
+            } else {
+                c
+            }
+        })
+        .take(max_len)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Value COPYed verbatim, its delimiters and escapes backslash escaped. For
+/// values like password hashes that must not be altered.
+pub fn escape_column(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(',', "\\,")
 }
 
 impl Address {
+    /// Text fields are made storable, see `text_column`.
     pub fn new(
         aid: u32,
         city: String,
@@ -161,9 +273,9 @@ impl Address {
     ) -> Address {
         Address {
             address_id: Some(aid),
-            city,
-            district,
-            street_name,
+            city: text_column(&city, ADDRESS_TEXT_LEN),
+            district: district.map(|d| text_column(&d, ADDRESS_TEXT_LEN)),
+            street_name: text_column(&street_name, ADDRESS_TEXT_LEN),
             house_number,
             zip_code,
             contract_id,
@@ -214,6 +326,8 @@ impl RecreatesForeignKeys for Address {
 #[derive(Debug)]
 pub struct Participant {
     pub participant_id: Option<u32>,
+    /// Unique login handle derived from the name.
+    pub login: String,
     pub name: String,
     pub access_level: u8,
     pub contract_id: u32,
@@ -221,11 +335,14 @@ pub struct Participant {
     pub balance_limit: Option<Decimal>,
     pub created_at: Option<String>,
     pub deleted_at: Option<String>,
+    /// Simultaneous calls of the participant, unlimited when `None`.
+    pub channel_limit: Option<u32>,
 }
 
 impl Participant {
     pub fn new(
         pid: u32,
+        login: String,
         name: String,
         access_level: u8,
         contract_id: u32,
@@ -236,6 +353,7 @@ impl Participant {
     ) -> Participant {
         Participant {
             participant_id: Some(pid),
+            login,
             name,
             access_level,
             contract_id,
@@ -243,13 +361,23 @@ impl Participant {
             password,
             created_at,
             deleted_at,
+            channel_limit: None,
         }
     }
+
+    pub fn lifespan(&self) -> Lifespan {
+        Lifespan::from_strings(
+            self.created_at
+                .as_ref()
+                .expect("Participant without created_at"),
+            self.deleted_at.as_ref(),
+        )
+    }
 }
 
 impl SqlInsert for Participant {
     fn insert_header() -> String {
-        "participant(participant_id, name, access_level, contract_id, password, balance_limit, deleted_at)".to_string()
+        "participant(participant_id, login, name, access_level, contract_id, password, balance_limit, created_at, deleted_at, channel_limit)".to_string()
     }
 
     fn table_name() -> String {
@@ -260,26 +388,38 @@ impl SqlInsert for Participant {
 impl CommaDelimited for Participant {
     fn to_csv(&self) -> String {
         format!(
-            "{id},{name},{access},{cid},{pass},{limit},{del}\n",
+            "{id},{login},{name},{access},{cid},{pass},{limit},{cr},{del},{channels}\n",
             id = if self.participant_id.is_some() {
                 self.participant_id.unwrap().to_string()
             } else {
                 "nul_val".to_string()
             },
+            login = self.login,
             name = self.name,
             access = self.access_level,
             cid = self.contract_id,
-            pass = self.password,
+            pass = escape_column(&self.password),
             limit = if self.balance_limit.is_some() {
                 self.balance_limit.unwrap().to_string()
             } else {
                 "nul_val".to_string()
             },
+            cr = self.created_at.as_ref().unwrap_or(&"nul_val".to_string()),
             del = self.deleted_at.as_ref().unwrap_or(&"nul_val".to_string()),
+            channels = self
+                .channel_limit
+                .map_or("nul_val".to_string(), |l| l.to_string()),
         )
     }
 }
 
+impl AltersTable for Participant {
+    fn alter_table() -> &'static str {
+        "alter table participant add column if not exists login varchar(100); \
+    alter table participant add column if not exists channel_limit integer"
+    }
+}
+
 impl RecreatesForeignKeys for Participant {
     fn drop_fk() -> std::option::Option<&'static str> {
         Some("ALTER TABLE participant DROP CONSTRAINT IF EXISTS fk_participant_contract")
@@ -289,25 +429,120 @@ impl RecreatesForeignKeys for Participant {
     }
 }
 
+// `access_level` values of a participant. Every contract has exactly one
+// owner, admins only appear on contracts with more than one participant.
+pub const ACCESS_LEVEL_OWNER: u8 = 1;
+pub const ACCESS_LEVEL_ADMIN: u8 = 2;
+pub const ACCESS_LEVEL_USER: u8 = 3;
+
+pub const PERMISSION_CONTRACT_MANAGE: &str = "contract.manage";
+pub const PERMISSION_PARTICIPANTS_MANAGE: &str = "participants.manage";
+pub const PERMISSION_LIMITS_MANAGE: &str = "limits.manage";
+pub const PERMISSION_NUMBERS_MANAGE: &str = "numbers.manage";
+pub const PERMISSION_NUMBERS_REQUEST: &str = "numbers.request";
+pub const PERMISSION_INVOICES_VIEW: &str = "invoices.view";
+pub const PERMISSION_INVOICES_PAY: &str = "invoices.pay";
+pub const PERMISSION_CALLS_VIEW_ALL: &str = "calls.view_all";
+pub const PERMISSION_CALLS_VIEW_OWN: &str = "calls.view_own";
+
+/// Permissions granted by an access level. Owners may do everything, admins
+/// run the contract's participants and numbers but not the contract itself
+/// or its payments, users only see their own calls and request numbers.
+pub fn access_level_permissions(access_level: u8) -> &'static [&'static str] {
+    match access_level {
+        ACCESS_LEVEL_OWNER => &[
+            PERMISSION_CONTRACT_MANAGE,
+            PERMISSION_PARTICIPANTS_MANAGE,
+            PERMISSION_LIMITS_MANAGE,
+            PERMISSION_NUMBERS_MANAGE,
+            PERMISSION_NUMBERS_REQUEST,
+            PERMISSION_INVOICES_VIEW,
+            PERMISSION_INVOICES_PAY,
+            PERMISSION_CALLS_VIEW_ALL,
+            PERMISSION_CALLS_VIEW_OWN,
+        ],
+        ACCESS_LEVEL_ADMIN => &[
+            PERMISSION_PARTICIPANTS_MANAGE,
+            PERMISSION_LIMITS_MANAGE,
+            PERMISSION_NUMBERS_MANAGE,
+            PERMISSION_NUMBERS_REQUEST,
+            PERMISSION_INVOICES_VIEW,
+            PERMISSION_CALLS_VIEW_ALL,
+            PERMISSION_CALLS_VIEW_OWN,
+        ],
+        ACCESS_LEVEL_USER => &[PERMISSION_NUMBERS_REQUEST, PERMISSION_CALLS_VIEW_OWN],
+        _ => panic!("Unknown access level {}", access_level),
+    }
+}
+
+#[derive(Debug)]
+pub struct Permission {
+    pub participant_id: u32,
+    pub permission: String,
+}
+
+impl SqlInsert for Permission {
+    fn insert_header() -> String {
+        "participant_permission(participant_id, permission)".to_string()
+    }
+
+    fn table_name() -> String {
+        "participant_permission".to_string()
+    }
+}
+
+impl CommaDelimited for Permission {
+    fn to_csv(&self) -> String {
+        format!("{},{}\n", self.participant_id, self.permission)
+    }
+}
+
+impl RecreatesForeignKeys for Permission {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some("alter table participant_permission drop constraint IF EXISTS fk_participant_permission_participant")
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table participant_permission add constraint fk_participant_permission_participant foreign key (participant_id) references participant(participant_id)")
+    }
+}
+
+impl CreatesTable for Permission {
+    fn create_table() -> &'static str {
+        "create table if not exists participant_permission (\
+            participant_id integer not null, \
+            permission varchar(32) not null, \
+            primary key (participant_id, permission))"
+    }
+}
+
+// `current_state` values of a voip number. A requested number becomes active
+// once its request is approved and goes back to the pool as free when the
+// request is rejected or expires. Active numbers of a contract that does not
+// pay get suspended. A released number stays in quarantine until
+// `quarantine_until` and is free for reuse afterwards.
+pub const NUMBER_STATE_FREE: u8 = 1;
+pub const NUMBER_STATE_REQUESTED: u8 = 2;
+pub const NUMBER_STATE_ACTIVE: u8 = 3;
+pub const NUMBER_STATE_SUSPENDED: u8 = 4;
+pub const NUMBER_STATE_QUARANTINED: u8 = 5;
+
 #[derive(Debug)]
 pub struct VoipNumber {
     pub number_id: Option<u32>,
-    phone_country_code: u16,
-    pub number: u32,
-    participant_id: Option<u32>,
+    pub number: PhoneNumber,
+    pub participant_id: Option<u32>,
     password: String,
-    current_state: u8,
-    foreign_block: bool,
-    quarantine_until: Option<String>,
-    activated: Option<String>,
-    deleted_at: Option<String>,
+    pub current_state: u8,
+    pub foreign_block: bool,
+    pub quarantine_until: Option<String>,
+    pub activated: Option<String>,
+    pub deleted_at: Option<String>,
 }
 
 impl VoipNumber {
     pub fn new(
         number_id: Option<u32>,
-        phone_country_code: u16,
-        number: u32,
+        number: PhoneNumber,
         participant_id: Option<u32>,
         password: String,
         current_state: u8,
@@ -318,7 +553,6 @@ impl VoipNumber {
     ) -> VoipNumber {
         VoipNumber {
             number_id,
-            phone_country_code,
             number,
             participant_id,
             password,
@@ -329,6 +563,20 @@ impl VoipNumber {
             deleted_at,
         }
     }
+
+    /// SIP digest password of the number's final `number`, its e164 form
+    /// without the `+` being the SIP username.
+    pub fn set_sip_password(&mut self, credentials: &mut credentials::Credentials) {
+        let username = self.number.e164().trim_start_matches('+').to_string();
+        self.password = credentials.sip_password(self.number_id.unwrap(), &username);
+    }
+
+    /// Numbers that were never activated have no lifespan and carry no traffic.
+    pub fn lifespan(&self) -> Option<Lifespan> {
+        self.activated
+            .as_ref()
+            .map(|a| Lifespan::from_strings(a, self.deleted_at.as_ref()))
+    }
 }
 
 impl SqlInsert for VoipNumber {
@@ -351,8 +599,8 @@ impl CommaDelimited for VoipNumber {
             } else {
                 "nul_val".to_string()
             },
-            pcc = self.phone_country_code,
-            num = self.number,
+            pcc = self.number.country_code,
+            num = self.number.national_digits(),
             pid = if self.participant_id.is_some() {
                 self.participant_id.unwrap().to_string()
             } else {
@@ -371,6 +619,14 @@ impl CommaDelimited for VoipNumber {
     }
 }
 
+impl AltersTable for VoipNumber {
+    /// National numbers are stored as digit strings, so that long foreign
+    /// numbers fit and leading zeros survive.
+    fn alter_table() -> &'static str {
+        "alter table voip_number alter column number type varchar(15) using number::text"
+    }
+}
+
 impl RecreatesForeignKeys for VoipNumber {
     fn drop_fk() -> std::option::Option<&'static str> {
         Some("alter table voip_number drop constraint IF EXISTS fk_voip_number_participant")
@@ -385,21 +641,50 @@ pub struct NumberRequest {
     participant_id: u32,
     number_id: u32,
     requested: String,
+    pub status: &'static str,
+    /// When the request was approved, rejected or expired, `None` while it
+    /// is pending.
+    pub decided: Option<String>,
 }
 
+// `status` values of a number request. Pending requests are younger than the
+// expiry period, the others carry the time of their decision.
+pub const REQUEST_PENDING: &str = "PENDING";
+pub const REQUEST_APPROVED: &str = "APPROVED";
+pub const REQUEST_REJECTED: &str = "REJECTED";
+pub const REQUEST_EXPIRED: &str = "EXPIRED";
+
 impl NumberRequest {
-    pub fn new(participant_id: u32, number_id: u32, requested: String) -> NumberRequest {
+    pub fn new(
+        participant_id: u32,
+        number_id: u32,
+        requested: String,
+        status: &'static str,
+        decided: Option<String>,
+    ) -> NumberRequest {
         NumberRequest {
             participant_id,
             number_id,
             requested,
+            status,
+            decided,
+        }
+    }
+
+    /// Time the requested number gets activated, the approval.
+    pub fn activation(&self) -> Option<DateTime<Utc>> {
+        if self.status != REQUEST_APPROVED {
+            return None;
         }
+        self.decided
+            .as_ref()
+            .map(|d| parse_date(d).with_timezone(&Utc))
     }
 }
 
 impl SqlInsert for NumberRequest {
     fn insert_header() -> String {
-        "number_request(participant_id, number_id, requested)".to_string()
+        "number_request(participant_id, number_id, requested, status, decided)".to_string()
     }
 
     fn table_name() -> String {
@@ -410,14 +695,23 @@ impl SqlInsert for NumberRequest {
 impl CommaDelimited for NumberRequest {
     fn to_csv(&self) -> String {
         format!(
-            "{pid},{nid},{req}\n",
+            "{pid},{nid},{req},{status},{dec}\n",
             pid = self.participant_id,
             nid = self.number_id,
-            req = self.requested
+            req = self.requested,
+            status = self.status,
+            dec = self.decided.as_ref().map_or("nul_val", |d| &d[..]),
         )
     }
 }
 
+impl AltersTable for NumberRequest {
+    fn alter_table() -> &'static str {
+        "alter table number_request add column if not exists status varchar(10); \
+    alter table number_request add column if not exists decided timestamp with time zone"
+    }
+}
+
 impl RecreatesForeignKeys for NumberRequest {
     fn drop_fk() -> std::option::Option<&'static str> {
         Some(
@@ -434,15 +728,85 @@ impl RecreatesForeignKeys for NumberRequest {
 #[derive(Debug)]
 pub struct PriceList {
     pub price_list_id: Option<u32>,
+    /// Kind of usage the list prices, `SERVICE_VOICE` unless set otherwise.
+    pub service: &'static str,
     tariffication_first: u8,
     tariffication_second: u8,
-    price_per_second: u16,
+    /// Hundredths of CZK per second of a call, per message of an SMS or per
+    /// started megabyte of a data session.
+    pub price_per_second: u16,
     pub phone_country_code: u16,
+    /// Leading digits of the E.164 number the price applies to, country code
+    /// included. The longest matching prefix wins.
+    pub prefix: String,
+    pub valid_from: String,
+    pub valid_to: Option<String>,
+}
+
+impl PriceList {
+    pub fn new(
+        price_list_id: Option<u32>,
+        phone_country_code: u16,
+        prefix: &str,
+        price_per_second: u16,
+        tariffication: (u8, u8),
+        valid_from: String,
+        valid_to: Option<String>,
+    ) -> PriceList {
+        assert!(
+            prefix.starts_with(&phone_country_code.to_string()),
+            "Price list prefix '{}' outside of +{}",
+            prefix,
+            phone_country_code
+        );
+        PriceList {
+            price_list_id,
+            service: SERVICE_VOICE,
+            tariffication_first: tariffication.0,
+            tariffication_second: tariffication.1,
+            price_per_second,
+            phone_country_code,
+            prefix: prefix.to_string(),
+            valid_from,
+            valid_to,
+        }
+    }
+
+    pub fn matches(&self, number: &PhoneNumber) -> bool {
+        number.country_code == self.phone_country_code
+            && number.e164()[1..].starts_with(&self.prefix)
+    }
+
+    pub fn is_valid_at(&self, date: &DateTime<FixedOffset>) -> bool {
+        parse_date(&self.valid_from) <= *date
+            && self
+                .valid_to
+                .as_ref()
+                .is_none_or(|to| *date < parse_date(to))
+    }
+
+    /// Seconds charged for a call of `length` seconds. The first block of
+    /// `tariffication_first` seconds is always charged whole, the rest is
+    /// rounded up to blocks of `tariffication_second` seconds.
+    pub fn billed_seconds(&self, length: u16) -> u32 {
+        let length = u32::from(length);
+        let first = u32::from(self.tariffication_first.max(1));
+        let second = u32::from(self.tariffication_second.max(1));
+        if length == 0 {
+            0
+        } else if length <= first {
+            first
+        } else {
+            first + (length - first).div_ceil(second) * second
+        }
+    }
 }
 
 impl SqlInsert for PriceList {
     fn insert_header() -> String {
-        "price_list(price_list_id, tariffication_first, tariffication_second, price_per_second, phone_country_code)".to_string()
+        "price_list(price_list_id, tariffication_first, tariffication_second, price_per_second, \
+            phone_country_code, prefix, valid_from, valid_to, service)"
+            .to_string()
     }
 
     fn table_name() -> String {
@@ -453,7 +817,7 @@ impl SqlInsert for PriceList {
 impl CommaDelimited for PriceList {
     fn to_csv(&self) -> String {
         format!(
-            "{pid},{t1},{t2},{pps},{pcc}\n",
+            "{pid},{t1},{t2},{pps},{pcc},{prefix},{from},{to},{service}\n",
             pid = if self.price_list_id.is_some() {
                 self.price_list_id.unwrap().to_string()
             } else {
@@ -463,10 +827,23 @@ impl CommaDelimited for PriceList {
             t2 = self.tariffication_second,
             pps = self.price_per_second,
             pcc = self.phone_country_code,
+            prefix = self.prefix,
+            from = self.valid_from,
+            to = self.valid_to.as_ref().map_or("nul_val", |v| &v[..]),
+            service = self.service,
         )
     }
 }
 
+impl AltersTable for PriceList {
+    fn alter_table() -> &'static str {
+        "alter table price_list add column if not exists prefix varchar(15); \
+    alter table price_list add column if not exists valid_from timestamptz; \
+    alter table price_list add column if not exists valid_to timestamptz; \
+    alter table price_list add column if not exists service varchar(5) default 'VOICE'"
+    }
+}
+
 impl RecreatesForeignKeys for PriceList {
     fn drop_fk() -> std::option::Option<&'static str> {
         None
@@ -476,49 +853,239 @@ impl RecreatesForeignKeys for PriceList {
     }
 }
 
+// `service` values of a price list.
+pub const SERVICE_VOICE: &str = "VOICE";
+pub const SERVICE_SMS: &str = "SMS";
+pub const SERVICE_DATA: &str = "DATA";
+
+/// Price in CZK of `units` seconds, messages or megabytes at a price per unit
+/// kept in hundredths of CZK.
+pub fn units_price(units: u32, unit_price: u16) -> Decimal {
+    Decimal::new(i64::from(units) * i64::from(unit_price), 2)
+}
+
+/// Subscription plan with a monthly fee, billed through its invoice item, and
+/// minutes included in the fee.
+#[derive(Debug)]
+pub struct Plan {
+    pub plan_id: u32,
+    pub name: String,
+    pub monthly_fee: Decimal,
+    pub included_minutes: u32,
+    pub invoice_item_id: u32,
+}
+
+impl SqlInsert for Plan {
+    fn insert_header() -> String {
+        "plan(plan_id, name, monthly_fee, included_minutes, invoice_item_id)".to_string()
+    }
+
+    fn table_name() -> String {
+        "plan".to_string()
+    }
+}
+
+impl CommaDelimited for Plan {
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{}\n",
+            self.plan_id, self.name, self.monthly_fee, self.included_minutes, self.invoice_item_id
+        )
+    }
+}
+
+impl RecreatesForeignKeys for Plan {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some("alter table plan drop constraint IF EXISTS fk_plan_invoice_item")
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table plan add constraint fk_plan_invoice_item foreign key (invoice_item_id) references invoice_item(item_id)")
+    }
+}
+
+impl CreatesTable for Plan {
+    fn create_table() -> &'static str {
+        "create table if not exists plan (\
+            plan_id serial primary key, \
+            name varchar(50) not null, \
+            monthly_fee numeric(10, 2) not null, \
+            included_minutes integer not null, \
+            invoice_item_id integer not null)"
+    }
+}
+
+/// Price of a destination on a plan overriding the price list entry with the
+/// same prefix.
+#[derive(Debug)]
+pub struct PlanRate {
+    pub plan_id: u32,
+    pub prefix: String,
+    /// Hundredths of CZK like in the price list.
+    pub price_per_second: u16,
+}
+
+impl SqlInsert for PlanRate {
+    fn insert_header() -> String {
+        "plan_rate(plan_id, prefix, price_per_second)".to_string()
+    }
+
+    fn table_name() -> String {
+        "plan_rate".to_string()
+    }
+}
+
+impl CommaDelimited for PlanRate {
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{}\n",
+            self.plan_id, self.prefix, self.price_per_second
+        )
+    }
+}
+
+impl RecreatesForeignKeys for PlanRate {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some("alter table plan_rate drop constraint IF EXISTS fk_plan_rate_plan")
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table plan_rate add constraint fk_plan_rate_plan foreign key (plan_id) references plan(plan_id)")
+    }
+}
+
+impl CreatesTable for PlanRate {
+    fn create_table() -> &'static str {
+        "create table if not exists plan_rate (\
+            plan_id integer not null, \
+            prefix varchar(15) not null, \
+            price_per_second integer not null, \
+            primary key (plan_id, prefix))"
+    }
+}
+
+/// Plan of a contract for a period, a plan change ends one subscription and
+/// starts the next.
+#[derive(Debug)]
+pub struct Subscription {
+    pub subscription_id: u32,
+    pub contract_id: u32,
+    pub plan_id: u32,
+    pub valid_from: String,
+    pub valid_to: Option<String>,
+}
+
+impl Subscription {
+    pub fn lifespan(&self) -> Lifespan {
+        Lifespan::from_strings(&self.valid_from, self.valid_to.as_ref())
+    }
+}
+
+impl SqlInsert for Subscription {
+    fn insert_header() -> String {
+        "subscription(subscription_id, contract_id, plan_id, valid_from, valid_to)".to_string()
+    }
+
+    fn table_name() -> String {
+        "subscription".to_string()
+    }
+}
+
+impl CommaDelimited for Subscription {
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{}\n",
+            self.subscription_id,
+            self.contract_id,
+            self.plan_id,
+            self.valid_from,
+            self.valid_to.as_ref().map_or("nul_val", |v| &v[..])
+        )
+    }
+}
+
+impl RecreatesForeignKeys for Subscription {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some(
+            "alter table subscription drop constraint IF EXISTS fk_subscription_contract; \
+        alter table subscription drop constraint IF EXISTS fk_subscription_plan",
+        )
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table subscription add constraint fk_subscription_contract foreign key (contract_id) references contract(contract_id); \
+        alter table subscription add constraint fk_subscription_plan foreign key (plan_id) references plan(plan_id)")
+    }
+}
+
+impl CreatesTable for Subscription {
+    fn create_table() -> &'static str {
+        "create table if not exists subscription (\
+            subscription_id serial primary key, \
+            contract_id integer not null, \
+            plan_id integer not null, \
+            valid_from timestamp with time zone not null, \
+            valid_to timestamp with time zone)"
+    }
+}
+
+/// Call dispositions as Asterisk writes them to its CDRs.
+pub const DISPOSITION_ANSWERED: &str = "ANSWERED";
+pub const DISPOSITION_NO_ANSWER: &str = "NO ANSWER";
+pub const DISPOSITION_BUSY: &str = "BUSY";
+pub const DISPOSITION_FAILED: &str = "FAILED";
+pub const DISPOSITION_CONGESTION: &str = "CONGESTION";
+
+pub const LEG_TRANSFER: &str = "TRANSFER";
+pub const LEG_FORWARD: &str = "FORWARD";
+
 #[derive(Debug)]
 pub struct CallDetailRecord {
-    call_id: Option<u32>,
-    disposition: String,
-    source_num: String,
-    destination_num: String,
-    length: u16,
-    call_date: String,
-    number_id: u32,
-    incoming_outgoing: bool,
-    price_list_id: Option<u32>,
+    pub call_id: Option<u32>,
+    pub disposition: String,
+    pub source_num: PhoneNumber,
+    pub destination_num: PhoneNumber,
+    /// Whole duration of the call, ringing included.
+    pub length: u16,
+    pub ring_duration: u16,
+    /// Seconds from answer to hangup, zero for calls never answered.
+    pub billsec: u16,
+    /// Why the switch refused the call, e.g. a blocked destination.
+    pub failure_reason: Option<String>,
+    pub call_date: String,
+    pub number_id: u32,
+    pub incoming_outgoing: bool,
+    pub price_list_id: Option<u32>,
+    /// Call id of the first leg of the call, the call's own id for single legs.
+    pub linked_id: u32,
+    /// Position of the leg within the linked call starting with 1.
+    pub leg: u8,
+    /// How the leg came to be when it is not the first one, transfer or forward.
+    pub leg_type: Option<String>,
+    /// Charged amount in CZK, `None` for calls that are not billed.
+    pub price: Option<Decimal>,
+    /// Billed seconds covered by the minutes included in the plan.
+    pub included_seconds: u32,
 }
 
 impl CallDetailRecord {
-    pub fn new(
-        call_id: Option<u32>,
-        disposition: String,
-        source_num: String,
-        destination_num: String,
-        length: u16,
-        call_date: String,
-        number_id: u32,
-        incoming_outgoing: bool,
-        price_list_id: Option<u32>,
-    ) -> CallDetailRecord {
-        CallDetailRecord {
-            call_id,
-            disposition,
-            source_num,
-            destination_num,
-            length,
-            call_date,
-            number_id,
-            incoming_outgoing,
-            price_list_id,
+    /// The other party of the call, the one the call is rated by.
+    pub fn remote_num(&self) -> &PhoneNumber {
+        if self.incoming_outgoing {
+            &self.source_num
+        } else {
+            &self.destination_num
         }
     }
+
+    /// Only answered outgoing calls are charged to the caller.
+    pub fn is_billable(&self) -> bool {
+        !self.incoming_outgoing && self.disposition == DISPOSITION_ANSWERED && self.billsec > 0
+    }
 }
 
 impl SqlInsert for CallDetailRecord {
     fn insert_header() -> String {
         "call_detail_record(call_id, disposition, source_num, destination_num, length, \
-            call_date, number_id, incoming_outgoing, price_list_id)"
+            ring_duration, billsec, failure_reason, call_date, number_id, incoming_outgoing, \
+            price_list_id, linked_id, leg, leg_type, price, included_seconds)"
             .to_string()
     }
 
@@ -530,7 +1097,7 @@ impl SqlInsert for CallDetailRecord {
 impl CommaDelimited for CallDetailRecord {
     fn to_csv(&self) -> String {
         format!(
-            "{cid},{dis},{src},{dst},{len},{date},{nid},{io},{list}\n",
+            "{cid},{dis},{src},{dst},{len},{ring},{bill},{reason},{date},{nid},{io},{list},{linked},{leg},{kind},{price},{incl}\n",
             cid = if self.call_id.is_some() {
                 self.call_id.unwrap().to_string()
             } else {
@@ -540,6 +1107,9 @@ impl CommaDelimited for CallDetailRecord {
             src = self.source_num,
             dst = self.destination_num,
             len = self.length,
+            ring = self.ring_duration,
+            bill = self.billsec,
+            reason = self.failure_reason.as_ref().map_or("nul_val", |r| &r[..]),
             date = self.call_date,
             nid = self.number_id,
             io = self.incoming_outgoing,
@@ -548,10 +1118,30 @@ impl CommaDelimited for CallDetailRecord {
             } else {
                 "nul_val".to_string()
             },
+            linked = self.linked_id,
+            leg = self.leg,
+            kind = self.leg_type.as_ref().map_or("nul_val", |k| &k[..]),
+            price = self
+                .price
+                .map_or("nul_val".to_string(), |p| p.to_string()),
+            incl = self.included_seconds,
         )
     }
 }
 
+impl AltersTable for CallDetailRecord {
+    fn alter_table() -> &'static str {
+        "alter table call_detail_record add column if not exists ring_duration int; \
+    alter table call_detail_record add column if not exists billsec int; \
+    alter table call_detail_record add column if not exists failure_reason varchar(30); \
+    alter table call_detail_record add column if not exists linked_id int; \
+    alter table call_detail_record add column if not exists leg int; \
+    alter table call_detail_record add column if not exists leg_type varchar(10); \
+    alter table call_detail_record add column if not exists price numeric(10, 2); \
+    alter table call_detail_record add column if not exists included_seconds int"
+    }
+}
+
 impl RecreatesForeignKeys for CallDetailRecord {
     fn drop_fk() -> std::option::Option<&'static str> {
         Some(
@@ -568,7 +1158,7 @@ impl RecreatesForeignKeys for CallDetailRecord {
 #[derive(Debug)]
 pub struct InvoiceItem {
     pub item_id: Option<u32>,
-    item_name: String,
+    pub item_name: String,
     pub unit_cost: f32,
 }
 
@@ -619,13 +1209,13 @@ impl RecreatesForeignKeys for InvoiceItem {
 #[derive(Debug)]
 pub struct Invoice {
     pub invoice_number: u64,
-    amount: f32,
-    tax_value_percent: u8,
-    created_at: String,
-    taxable_period: String,
-    maturity: String,
-    paid: Option<String>,
-    contract_id: u32,
+    pub amount: f32,
+    pub tax_value_percent: u8,
+    pub created_at: String,
+    pub taxable_period: String,
+    pub maturity: String,
+    pub paid: Option<String>,
+    pub contract_id: u32,
 }
 
 impl Invoice {
@@ -689,10 +1279,10 @@ impl RecreatesForeignKeys for Invoice {
 
 #[derive(Debug)]
 pub struct InvoiceHasItems {
-    invoice_number: u64,
-    invoice_item_id: u32,
-    item_unit_cost: f32,
-    item_count: u16,
+    pub invoice_number: u64,
+    pub invoice_item_id: u32,
+    pub item_unit_cost: f32,
+    pub item_count: u16,
 }
 
 impl InvoiceHasItems {
@@ -745,3 +1335,395 @@ impl RecreatesForeignKeys for InvoiceHasItems {
         alter table invoice_has_items add constraint fk_ihi_invoice_item foreign key (invoice_item_id) references invoice_item(item_id)")
     }
 }
+
+#[derive(Debug)]
+pub struct SmsRecord {
+    pub sms_id: u32,
+    pub number_id: u32,
+    pub incoming: bool,
+    pub source_num: PhoneNumber,
+    pub destination_num: PhoneNumber,
+    pub sent_at: String,
+    /// Concatenated messages a long text was split into, each billed.
+    pub parts: u8,
+    pub price_list_id: Option<u32>,
+    /// Charged amount in CZK, `None` for incoming or unrated messages.
+    pub price: Option<Decimal>,
+}
+
+impl SmsRecord {
+    /// The other party of the message, the one it is rated by.
+    pub fn remote_num(&self) -> &PhoneNumber {
+        if self.incoming {
+            &self.source_num
+        } else {
+            &self.destination_num
+        }
+    }
+}
+
+impl SqlInsert for SmsRecord {
+    fn insert_header() -> String {
+        "sms_record(sms_id, number_id, incoming, source_num, destination_num, sent_at, parts, \
+            price_list_id, price)"
+            .to_string()
+    }
+
+    fn table_name() -> String {
+        "sms_record".to_string()
+    }
+}
+
+impl CommaDelimited for SmsRecord {
+    fn to_csv(&self) -> String {
+        format!(
+            "{id},{nid},{inc},{src},{dst},{sent},{parts},{list},{price}\n",
+            id = self.sms_id,
+            nid = self.number_id,
+            inc = self.incoming,
+            src = self.source_num,
+            dst = self.destination_num,
+            sent = self.sent_at,
+            parts = self.parts,
+            list = self
+                .price_list_id
+                .map_or("nul_val".to_string(), |v| v.to_string()),
+            price = self.price.map_or("nul_val".to_string(), |v| v.to_string()),
+        )
+    }
+}
+
+impl RecreatesForeignKeys for SmsRecord {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some(
+            "alter table sms_record drop constraint IF EXISTS fk_sms_record_voip_number; \
+        alter table sms_record drop constraint IF EXISTS fk_sms_record_price_list",
+        )
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table sms_record add constraint fk_sms_record_voip_number foreign key (number_id) references voip_number(number_id); \
+        alter table sms_record add constraint fk_sms_record_price_list foreign key (price_list_id) references price_list(price_list_id)")
+    }
+}
+
+impl CreatesTable for SmsRecord {
+    fn create_table() -> &'static str {
+        "create table if not exists sms_record (\
+            sms_id serial primary key, \
+            number_id integer not null, \
+            incoming boolean not null, \
+            source_num varchar(30) not null, \
+            destination_num varchar(30) not null, \
+            sent_at timestamp with time zone not null, \
+            parts smallint not null, \
+            price_list_id integer, \
+            price numeric(10, 2))"
+    }
+}
+
+/// Mobile data session of a number in the network of `phone_country_code`,
+/// roaming when abroad.
+#[derive(Debug)]
+pub struct DataSession {
+    pub session_id: u32,
+    pub number_id: u32,
+    pub phone_country_code: u16,
+    pub started_at: String,
+    pub duration: u32,
+    pub bytes_up: u64,
+    pub bytes_down: u64,
+    pub price_list_id: Option<u32>,
+    pub price: Option<Decimal>,
+}
+
+impl DataSession {
+    /// Megabytes charged, every started one counts.
+    pub fn billed_megabytes(&self) -> u64 {
+        (self.bytes_up + self.bytes_down).div_ceil(1_000_000)
+    }
+}
+
+impl SqlInsert for DataSession {
+    fn insert_header() -> String {
+        "data_session(session_id, number_id, phone_country_code, started_at, duration, bytes_up, \
+            bytes_down, price_list_id, price)"
+            .to_string()
+    }
+
+    fn table_name() -> String {
+        "data_session".to_string()
+    }
+}
+
+impl CommaDelimited for DataSession {
+    fn to_csv(&self) -> String {
+        format!(
+            "{id},{nid},{pcc},{start},{dur},{up},{down},{list},{price}\n",
+            id = self.session_id,
+            nid = self.number_id,
+            pcc = self.phone_country_code,
+            start = self.started_at,
+            dur = self.duration,
+            up = self.bytes_up,
+            down = self.bytes_down,
+            list = self
+                .price_list_id
+                .map_or("nul_val".to_string(), |v| v.to_string()),
+            price = self.price.map_or("nul_val".to_string(), |v| v.to_string()),
+        )
+    }
+}
+
+impl RecreatesForeignKeys for DataSession {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some(
+            "alter table data_session drop constraint IF EXISTS fk_data_session_voip_number; \
+        alter table data_session drop constraint IF EXISTS fk_data_session_price_list",
+        )
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table data_session add constraint fk_data_session_voip_number foreign key (number_id) references voip_number(number_id); \
+        alter table data_session add constraint fk_data_session_price_list foreign key (price_list_id) references price_list(price_list_id)")
+    }
+}
+
+impl CreatesTable for DataSession {
+    fn create_table() -> &'static str {
+        "create table if not exists data_session (\
+            session_id serial primary key, \
+            number_id integer not null, \
+            phone_country_code smallint not null, \
+            started_at timestamp with time zone not null, \
+            duration integer not null, \
+            bytes_up bigint not null, \
+            bytes_down bigint not null, \
+            price_list_id integer, \
+            price numeric(10, 2))"
+    }
+}
+
+#[derive(Debug)]
+pub struct Notification {
+    pub notification_id: Option<u32>,
+    pub contract_id: u32,
+    pub participant_id: Option<u32>,
+    pub kind: String,
+    pub billing_period: String,
+    pub spent: Decimal,
+    pub limit_amount: Decimal,
+    pub call_id: Option<u32>,
+    pub created_at: String,
+}
+
+impl SqlInsert for Notification {
+    fn insert_header() -> String {
+        "notification(notification_id, contract_id, participant_id, kind, billing_period, spent, \
+            limit_amount, call_id, created_at)"
+            .to_string()
+    }
+
+    fn table_name() -> String {
+        "notification".to_string()
+    }
+}
+
+impl CommaDelimited for Notification {
+    fn to_csv(&self) -> String {
+        format!(
+            "{id},{cid},{pid},{kind},{period},{spent},{limit},{call},{cr}\n",
+            id = self
+                .notification_id
+                .map_or("nul_val".to_string(), |v| v.to_string()),
+            cid = self.contract_id,
+            pid = self
+                .participant_id
+                .map_or("nul_val".to_string(), |v| v.to_string()),
+            kind = self.kind,
+            period = self.billing_period,
+            spent = self.spent,
+            limit = self.limit_amount,
+            call = self
+                .call_id
+                .map_or("nul_val".to_string(), |v| v.to_string()),
+            cr = self.created_at,
+        )
+    }
+}
+
+impl RecreatesForeignKeys for Notification {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some(
+            "alter table notification drop constraint IF EXISTS fk_notification_contract; \
+        alter table notification drop constraint IF EXISTS fk_notification_participant",
+        )
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table notification add constraint fk_notification_contract foreign key (contract_id) references contract(contract_id); \
+        alter table notification add constraint fk_notification_participant foreign key (participant_id) references participant(participant_id)")
+    }
+}
+
+impl CreatesTable for Notification {
+    fn create_table() -> &'static str {
+        "create table if not exists notification (\
+            notification_id serial primary key, \
+            contract_id integer not null, \
+            participant_id integer, \
+            kind varchar(32) not null, \
+            billing_period date not null, \
+            spent numeric(12, 2) not null, \
+            limit_amount numeric(12, 2) not null, \
+            call_id integer, \
+            created_at timestamp with time zone not null)"
+    }
+}
+
+#[derive(Debug)]
+pub struct CreditNote {
+    pub credit_note_number: u64,
+    pub invoice_number: u64,
+    pub contract_id: u32,
+    pub amount: f32,
+    pub reason: String,
+    pub created_at: String,
+}
+
+impl SqlInsert for CreditNote {
+    fn insert_header() -> String {
+        "credit_note(credit_note_number, invoice_number, contract_id, amount, reason, created_at)"
+            .to_string()
+    }
+
+    fn table_name() -> String {
+        "credit_note".to_string()
+    }
+}
+
+impl CommaDelimited for CreditNote {
+    fn to_csv(&self) -> String {
+        format!(
+            "{num},{inum},{cid},{amount},{reason},{cr}\n",
+            num = self.credit_note_number,
+            inum = self.invoice_number,
+            cid = self.contract_id,
+            amount = self.amount,
+            reason = self.reason,
+            cr = self.created_at,
+        )
+    }
+}
+
+impl RecreatesForeignKeys for CreditNote {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some(
+            "alter table credit_note drop constraint IF EXISTS fk_credit_note_invoice; \
+        alter table credit_note drop constraint IF EXISTS fk_credit_note_contract",
+        )
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table credit_note add constraint fk_credit_note_invoice foreign key (invoice_number) references invoice(invoice_number); \
+        alter table credit_note add constraint fk_credit_note_contract foreign key (contract_id) references contract(contract_id)")
+    }
+}
+
+impl CreatesTable for CreditNote {
+    fn create_table() -> &'static str {
+        "create table if not exists credit_note (\
+            credit_note_number integer primary key, \
+            invoice_number integer not null, \
+            contract_id integer not null, \
+            amount real not null, \
+            reason varchar(64) not null, \
+            created_at timestamp with time zone not null)"
+    }
+}
+
+#[derive(Debug)]
+pub struct PaymentReminder {
+    pub reminder_id: Option<u32>,
+    pub invoice_number: u64,
+    pub contract_id: u32,
+    pub reminder_level: u8,
+    pub amount_due: f32,
+    pub sent_at: String,
+}
+
+impl SqlInsert for PaymentReminder {
+    fn insert_header() -> String {
+        "payment_reminder(reminder_id, invoice_number, contract_id, reminder_level, amount_due, sent_at)"
+            .to_string()
+    }
+
+    fn table_name() -> String {
+        "payment_reminder".to_string()
+    }
+}
+
+impl CommaDelimited for PaymentReminder {
+    fn to_csv(&self) -> String {
+        format!(
+            "{id},{inum},{cid},{level},{due},{sent}\n",
+            id = self
+                .reminder_id
+                .map_or("nul_val".to_string(), |v| v.to_string()),
+            inum = self.invoice_number,
+            cid = self.contract_id,
+            level = self.reminder_level,
+            due = self.amount_due,
+            sent = self.sent_at,
+        )
+    }
+}
+
+impl RecreatesForeignKeys for PaymentReminder {
+    fn drop_fk() -> std::option::Option<&'static str> {
+        Some(
+            "alter table payment_reminder drop constraint IF EXISTS fk_payment_reminder_invoice; \
+        alter table payment_reminder drop constraint IF EXISTS fk_payment_reminder_contract",
+        )
+    }
+    fn recreate_fk() -> std::option::Option<&'static str> {
+        Some("alter table payment_reminder add constraint fk_payment_reminder_invoice foreign key (invoice_number) references invoice(invoice_number); \
+        alter table payment_reminder add constraint fk_payment_reminder_contract foreign key (contract_id) references contract(contract_id)")
+    }
+}
+
+impl CreatesTable for PaymentReminder {
+    fn create_table() -> &'static str {
+        "create table if not exists payment_reminder (\
+            reminder_id serial primary key, \
+            invoice_number integer not null, \
+            contract_id integer not null, \
+            reminder_level smallint not null, \
+            amount_due real not null, \
+            sent_at timestamp with time zone not null)"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voip_number_keeps_long_and_zero_led_national_numbers() {
+        for (e164, national) in [
+            ("+4915112345678", "15112345678"),
+            ("+390612345678", "0612345678"),
+        ] {
+            let number = VoipNumber::new(
+                Some(1),
+                e164.parse().unwrap(),
+                None,
+                String::new(),
+                NUMBER_STATE_FREE,
+                false,
+                None,
+                None,
+                None,
+            );
+            let csv = number.to_csv();
+            let columns: Vec<&str> = csv.split(',').collect();
+            assert_eq!(columns[2], national);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use super::phone_number::PhoneNumber;
+use fake::Fake;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Inclusive range of national numbers available under a country code,
+/// written as `{country_code}:{first}-{last}` in the scenario file.
+#[derive(Debug, Clone)]
+pub struct NumberRange {
+    pub phone_country_code: u16,
+    pub first: u64,
+    pub last: u64,
+}
+
+impl NumberRange {
+    fn size(&self) -> u64 {
+        self.last - self.first + 1
+    }
+}
+
+impl FromStr for NumberRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<NumberRange, String> {
+        let (pcc, range) = value
+            .split_once(':')
+            .ok_or_else(|| format!("Missing country code in number range '{}'", value))?;
+        let (first, last) = range
+            .split_once('-')
+            .ok_or_else(|| format!("Malformed number range '{}'", value))?;
+        let range = NumberRange {
+            phone_country_code: pcc.trim().parse().map_err(|_| "Bad country code")?,
+            first: first.trim().parse().map_err(|_| "Bad range start")?,
+            last: last.trim().parse().map_err(|_| "Bad range end")?,
+        };
+        if range.first > range.last {
+            return Err(format!("Empty number range '{}'", value));
+        }
+        PhoneNumber::new(range.phone_country_code, range.first)?;
+        PhoneNumber::new(range.phone_country_code, range.last)?;
+        Ok(range)
+    }
+}
+
+/// Contiguous block of direct inward dialing numbers owned by one contract.
+#[derive(Debug)]
+pub struct DidBlock {
+    pub phone_country_code: u16,
+    pub first: u64,
+    pub size: u32,
+    pub contract_id: u32,
+    next: u64,
+}
+
+/// Hands out numbers that are unique within the run and among the numbers
+/// already stored, drawn from the configured ranges.
+pub struct NumberPlan {
+    pub ranges: Vec<NumberRange>,
+    pub blocks: Vec<DidBlock>,
+    used: HashSet<PhoneNumber>,
+    in_blocks: HashSet<PhoneNumber>,
+}
+
+impl NumberPlan {
+    pub fn new(ranges: Vec<NumberRange>) -> NumberPlan {
+        NumberPlan {
+            ranges,
+            blocks: vec![],
+            used: HashSet::new(),
+            in_blocks: HashSet::new(),
+        }
+    }
+
+    /// Marks a number as taken, e.g. one loaded from the database.
+    pub fn reserve(&mut self, number: PhoneNumber) {
+        self.used.insert(number);
+    }
+
+    fn number(phone_country_code: u16, national: u64) -> PhoneNumber {
+        PhoneNumber::new(phone_country_code, national)
+            .expect("Number ranges are validated when parsed")
+    }
+
+    fn range_for(&self, phone_country_code: u16) -> &NumberRange {
+        let ranges: Vec<&NumberRange> = self
+            .ranges
+            .iter()
+            .filter(|r| r.phone_country_code == phone_country_code)
+            .collect();
+        assert!(
+            !ranges.is_empty(),
+            "No number range configured for +{}",
+            phone_country_code
+        );
+        ranges[(0..ranges.len()).fake::<usize>()]
+    }
+
+    /// Random unused number from one of the country's ranges. Panics when all
+    /// of them are taken.
+    pub fn allocate(&mut self, phone_country_code: u16) -> PhoneNumber {
+        for _ in 0..1000 {
+            let range = self.range_for(phone_country_code);
+            let number = NumberPlan::number(phone_country_code, (range.first..=range.last).fake());
+            if self.used.insert(number) {
+                return number;
+            }
+        }
+        // Ranges are nearly full, fall back to scanning for a free number.
+        let free = self
+            .ranges
+            .iter()
+            .filter(|r| r.phone_country_code == phone_country_code)
+            .flat_map(|r| r.first..=r.last)
+            .map(|n| NumberPlan::number(phone_country_code, n))
+            .find(|n| !self.used.contains(n))
+            .unwrap_or_else(|| panic!("Number ranges for +{} are exhausted", phone_country_code));
+        self.used.insert(free);
+        free
+    }
+
+    /// Reserves `size` contiguous unused numbers for the contract, aligned to
+    /// the block size within the range. Returns the block index.
+    pub fn allocate_block(
+        &mut self,
+        phone_country_code: u16,
+        size: u32,
+        contract_id: u32,
+    ) -> usize {
+        for _ in 0..1000 {
+            let range = self.range_for(phone_country_code);
+            let blocks = range.size() / u64::from(size);
+            if blocks == 0 {
+                continue;
+            }
+            let first = range.first + (0..blocks).fake::<u64>() * u64::from(size);
+            let numbers: Vec<PhoneNumber> = (first..first + u64::from(size))
+                .map(|n| NumberPlan::number(phone_country_code, n))
+                .collect();
+            if numbers.iter().any(|n| self.used.contains(n)) {
+                continue;
+            }
+            for n in numbers {
+                self.used.insert(n);
+                self.in_blocks.insert(n);
+            }
+            self.blocks.push(DidBlock {
+                phone_country_code,
+                first,
+                size,
+                contract_id,
+                next: first,
+            });
+            return self.blocks.len() - 1;
+        }
+        panic!(
+            "No free block of {} numbers for +{}",
+            size, phone_country_code
+        );
+    }
+
+    /// Next number of the block, a regular number once the block is used up.
+    pub fn allocate_from_block(&mut self, block: usize) -> PhoneNumber {
+        let b = &mut self.blocks[block];
+        if b.next - b.first < u64::from(b.size) {
+            b.next += 1;
+            return NumberPlan::number(b.phone_country_code, b.next - 1);
+        }
+        let pcc = b.phone_country_code;
+        self.allocate(pcc)
+    }
+
+    pub fn in_block(&self, number: &PhoneNumber) -> bool {
+        self.in_blocks.contains(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(range: &str) -> NumberPlan {
+        NumberPlan::new(vec![range.parse().unwrap()])
+    }
+
+    #[test]
+    fn allocated_numbers_are_unique_and_in_range() {
+        let mut plan = plan("420:601000000-601009999");
+        let numbers: HashSet<PhoneNumber> = (0..5000).map(|_| plan.allocate(420)).collect();
+        assert_eq!(numbers.len(), 5000);
+        assert!(numbers
+            .iter()
+            .all(|n| n.country_code == 420 && (601_000_000..=601_009_999).contains(&n.national)));
+    }
+
+    #[test]
+    fn reserved_numbers_are_never_handed_out() {
+        let mut plan = plan("420:601000000-601000099");
+        let reserved: Vec<PhoneNumber> = (601_000_000..601_000_100)
+            .step_by(2)
+            .map(|n| PhoneNumber::new(420, n).unwrap())
+            .collect();
+        for number in &reserved {
+            plan.reserve(*number);
+        }
+        let numbers: HashSet<PhoneNumber> = (0..50).map(|_| plan.allocate(420)).collect();
+        assert_eq!(numbers.len(), 50);
+        assert!(reserved.iter().all(|n| !numbers.contains(n)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Number ranges for +420 are exhausted")]
+    fn exhausted_range_fails() {
+        let mut plan = plan("420:601000000-601000009");
+        plan.reserve(PhoneNumber::new(420, 601_000_000).unwrap());
+        for _ in 0..10 {
+            plan.allocate(420);
+        }
+    }
+
+    #[test]
+    fn blocks_are_contiguous_and_kept_apart_from_single_numbers() {
+        let mut plan = plan("420:601000000-601099999");
+        let mut singles: Vec<PhoneNumber> = vec![];
+        let mut blocks: Vec<Vec<PhoneNumber>> = vec![];
+        for contract_id in 1..=20 {
+            singles.extend((0..10).map(|_| plan.allocate(420)));
+            let block = plan.allocate_block(420, 10, contract_id);
+            blocks.push((0..10).map(|_| plan.allocate_from_block(block)).collect());
+        }
+
+        for (block, numbers) in plan.blocks.iter().zip(blocks.iter()) {
+            assert_eq!(block.first % 10, 0);
+            let expected: Vec<u64> = (block.first..block.first + 10).collect();
+            let actual: Vec<u64> = numbers.iter().map(|n| n.national).collect();
+            assert_eq!(actual, expected);
+            assert!(numbers.iter().all(|n| plan.in_block(n)));
+        }
+        assert!(singles.iter().all(|n| !plan.in_block(n)));
+        let all: HashSet<PhoneNumber> = singles
+            .iter()
+            .chain(blocks.iter().flatten())
+            .copied()
+            .collect();
+        assert_eq!(all.len(), singles.len() + 200);
+
+        // A used up block falls back to regular numbers.
+        let extra = plan.allocate_from_block(0);
+        assert!(!plan.in_block(&extra) && !all.contains(&extra));
+    }
+
+    #[test]
+    #[should_panic(expected = "No free block of 10 numbers for +420")]
+    fn missing_room_for_a_block_fails() {
+        let mut plan = plan("420:601000000-601000024");
+        for contract_id in 1..=3 {
+            plan.allocate_block(420, 10, contract_id);
+        }
+    }
+}
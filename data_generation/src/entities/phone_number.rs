@@ -0,0 +1,203 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Longest number E.164 allows, country code included.
+const MAX_DIGITS: usize = 15;
+
+/// Two digit country codes, every other code starting with 1 or 7 is one digit
+/// long and the rest are three digits. E.164 codes are prefix free, so this is
+/// enough to split a number into country code and national part.
+const TWO_DIGIT_CODES: [u16; 44] = [
+    20, 27, 30, 31, 32, 33, 34, 36, 39, 40, 41, 43, 44, 45, 46, 47, 48, 49, 51, 52, 53, 54, 55, 56,
+    57, 58, 60, 61, 62, 63, 64, 65, 66, 81, 82, 84, 86, 90, 91, 92, 93, 94, 95, 98,
+];
+
+/// Phone number in E.164 form, a country code and a national significant
+/// number. The national number keeps its digit count so leading zeros survive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PhoneNumber {
+    pub country_code: u16,
+    pub national: u64,
+    digits: u8,
+}
+
+impl PhoneNumber {
+    /// Shortest and longest national significant number for countries with a
+    /// known numbering plan, others accept anything E.164 allows.
+    pub fn national_length(country_code: u16) -> (usize, usize) {
+        match country_code {
+            420 | 421 | 48 => (9, 9),
+            49 => (6, 13),
+            43 => (4, 13),
+            1 => (10, 10),
+            _ => (4, MAX_DIGITS - country_code.to_string().len()),
+        }
+    }
+
+    pub fn new(country_code: u16, national: u64) -> Result<PhoneNumber, String> {
+        let digits = national.to_string().len();
+        PhoneNumber::with_digits(country_code, national, digits)
+    }
+
+    fn with_digits(country_code: u16, national: u64, digits: usize) -> Result<PhoneNumber, String> {
+        let cc_digits = country_code.to_string().len();
+        if country_code == 0 || cc_digits > 3 {
+            return Err(format!("Invalid country code {}", country_code));
+        }
+        let (min, max) = PhoneNumber::national_length(country_code);
+        if digits < min || digits > max || cc_digits + digits > MAX_DIGITS {
+            return Err(format!(
+                "National number of +{} must have {} to {} digits",
+                country_code, min, max
+            ));
+        }
+        Ok(PhoneNumber {
+            country_code,
+            national,
+            digits: digits as u8,
+        })
+    }
+
+    pub fn national_digits(&self) -> String {
+        format!(
+            "{:0width$}",
+            self.national,
+            width = usize::from(self.digits)
+        )
+    }
+
+    /// `+420601234567`
+    pub fn e164(&self) -> String {
+        format!("+{}{}", self.country_code, self.national_digits())
+    }
+
+    /// National number in groups of three digits, `601 234 567`.
+    pub fn national_format(&self) -> String {
+        let digits = self.national_digits();
+        let head = digits.len() % 3;
+        let mut groups: Vec<&str> = vec![];
+        if head > 0 {
+            groups.push(&digits[..head]);
+        }
+        groups.extend((head..digits.len()).step_by(3).map(|i| &digits[i..i + 3]));
+        groups.join(" ")
+    }
+
+    /// `+420 601 234 567`
+    pub fn international_format(&self) -> String {
+        format!("+{} {}", self.country_code, self.national_format())
+    }
+}
+
+impl FromStr for PhoneNumber {
+    type Err = String;
+
+    /// Accepts E.164 numbers with optional spaces, dashes or dots between the
+    /// digits, `00` may stand in for the leading `+`.
+    fn from_str(value: &str) -> Result<PhoneNumber, String> {
+        let trimmed = value.trim();
+        let rest = if let Some(rest) = trimmed.strip_prefix('+') {
+            rest
+        } else if let Some(rest) = trimmed.strip_prefix("00") {
+            rest
+        } else {
+            return Err(format!("'{}' is not in international format", value));
+        };
+        let mut digits = String::with_capacity(MAX_DIGITS);
+        for c in rest.chars() {
+            match c {
+                '0'..='9' => digits.push(c),
+                ' ' | '-' | '.' => continue,
+                _ => return Err(format!("Unexpected '{}' in phone number '{}'", c, value)),
+            }
+        }
+        if digits.len() < 2 || digits.len() > MAX_DIGITS {
+            return Err(format!("'{}' has an invalid length", value));
+        }
+
+        let cc_len = if digits.starts_with('1') || digits.starts_with('7') {
+            1
+        } else if TWO_DIGIT_CODES.contains(&digits[..2].parse::<u16>().unwrap()) {
+            2
+        } else {
+            3
+        };
+        if digits.len() <= cc_len {
+            return Err(format!("'{}' has no national number", value));
+        }
+        let country_code = digits[..cc_len].parse::<u16>().unwrap();
+        let national = &digits[cc_len..];
+        PhoneNumber::with_digits(
+            country_code,
+            national.parse::<u64>().unwrap(),
+            national.len(),
+        )
+    }
+}
+
+impl fmt::Display for PhoneNumber {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.e164())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> PhoneNumber {
+        value.parse().expect("Valid number")
+    }
+
+    #[test]
+    fn splits_one_digit_country_codes() {
+        let us = parse("+1 202-555-0143");
+        assert_eq!((us.country_code, us.national), (1, 2025550143));
+        let ru = parse("+7 495 123 45 67");
+        assert_eq!((ru.country_code, ru.national), (7, 4951234567));
+    }
+
+    #[test]
+    fn splits_two_digit_country_codes() {
+        for (value, country_code) in [
+            ("+49 30 1234567", 49),
+            ("+44 20 7946 0958", 44),
+            ("+92 300 1234567", 92),
+            ("+93 70 123 4567", 93),
+            ("+94 71 234 5678", 94),
+            ("+95 9 123 456 789", 95),
+            ("+98 912 345 6789", 98),
+        ] {
+            assert_eq!(parse(value).country_code, country_code, "{}", value);
+        }
+    }
+
+    #[test]
+    fn splits_three_digit_country_codes() {
+        let cz = parse("00420 601 234 567");
+        assert_eq!((cz.country_code, cz.national), (420, 601234567));
+        assert_eq!(parse("+421.905.123.456").country_code, 421);
+        assert_eq!(parse("+971 50 123 4567").country_code, 971);
+        assert_eq!(parse("+380 44 123 4567").country_code, 380);
+    }
+
+    #[test]
+    fn keeps_leading_zeros_of_national_numbers() {
+        let it = parse("+39 06 1234 5678");
+        assert_eq!((it.country_code, it.national), (39, 612345678));
+        assert_eq!(it.national_digits(), "0612345678");
+        assert_eq!(it.e164(), "+390612345678");
+        assert_eq!(parse(&it.e164()), it);
+        assert_ne!(it, parse("+39 6 1234 5678"));
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        assert!("420601234567".parse::<PhoneNumber>().is_err());
+        assert!("+420 60123456".parse::<PhoneNumber>().is_err());
+        assert!("+420 601 234 567x".parse::<PhoneNumber>().is_err());
+        assert!("+1 202 555 014".parse::<PhoneNumber>().is_err());
+        assert!("+420".parse::<PhoneNumber>().is_err());
+        assert!("+1234567890123456".parse::<PhoneNumber>().is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use super::generators::date_within;
+use super::*;
+use crate::config::Scenario;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Timelike, Utc};
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal, Zipf};
+
+/// Longest call the duration distribution is cut off at, three hours.
+const MAX_CALL_SECONDS: f64 = 10_800.0;
+
+/// Days with a fixed date that are public holidays in Czechia.
+const FIXED_HOLIDAYS: [(u32, u32); 11] = [
+    (1, 1),
+    (5, 1),
+    (5, 8),
+    (7, 5),
+    (7, 6),
+    (9, 28),
+    (10, 28),
+    (11, 17),
+    (12, 24),
+    (12, 25),
+    (12, 26),
+];
+
+/// Easter Sunday of the year, anonymous Gregorian algorithm.
+fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = (19 * a + b - b / 4 - (8 * b + 13) / 25 + 15) % 30;
+    let e = (32 + 2 * (b % 4) + 2 * (c / 4) - d - c % 4) % 7;
+    let f = d + e - 7 * ((a + 11 * d + 22 * e) / 451) + 114;
+    NaiveDate::from_ymd(year, (f / 31) as u32, (f % 31 + 1) as u32)
+}
+
+/// Czech public holidays including Good Friday and Easter Monday.
+pub fn is_czech_holiday(date: NaiveDate) -> bool {
+    let easter = easter_sunday(date.year());
+    FIXED_HOLIDAYS.contains(&(date.month(), date.day()))
+        || date == easter - Duration::days(2)
+        || date == easter + Duration::days(1)
+}
+
+const DISPOSITIONS: [&str; 5] = [
+    DISPOSITION_ANSWERED,
+    DISPOSITION_NO_ANSWER,
+    DISPOSITION_BUSY,
+    DISPOSITION_FAILED,
+    DISPOSITION_CONGESTION,
+];
+
+/// How a call attempt ended and how long it took.
+pub struct CallOutcome {
+    pub disposition: &'static str,
+    pub ring_duration: u16,
+    pub billsec: u16,
+}
+
+/// Decides which number places a call, when and for how long. Call volume
+/// per number follows a Zipf distribution, the time of the call a weekly
+/// profile with holidays and the duration a log-normal distribution.
+pub struct TrafficModel {
+    zipf: Zipf<f64>,
+    ranks: Vec<usize>,
+    duration: LogNormal<f64>,
+    dispositions: WeightedIndex<u32>,
+    hour_weights: Vec<u32>,
+    weekday_weights: Vec<u32>,
+    holiday_weight: u32,
+    max_weight: u32,
+}
+
+impl TrafficModel {
+    /// Model for numbers with the given relative weights. Which of them get
+    /// the most traffic is chosen at random, numbers with a higher weight are
+    /// more likely to rank high.
+    pub fn new(scenario: &Scenario, weights: &[f64]) -> TrafficModel {
+        let numbers = weights.len();
+        assert!(numbers > 0, "No numbers to generate traffic for");
+        assert_eq!(
+            scenario.traffic_hour_weights.len(),
+            24,
+            "traffic_hour_weights needs a weight for every hour"
+        );
+        assert_eq!(
+            scenario.call_disposition_weights.len(),
+            DISPOSITIONS.len(),
+            "call_disposition_weights needs a weight for every disposition"
+        );
+        assert_eq!(
+            scenario.traffic_weekday_weights.len(),
+            7,
+            "traffic_weekday_weights needs a weight for every day of the week"
+        );
+        // Weighted random order, each number keyed by u^(1/weight).
+        let mut rng = rand::thread_rng();
+        let mut keys: Vec<(f64, usize)> = weights
+            .iter()
+            .enumerate()
+            .map(|(idx, w)| (rng.gen::<f64>().powf(1.0 / w.max(f64::MIN_POSITIVE)), idx))
+            .collect();
+        keys.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let ranks: Vec<usize> = keys.into_iter().map(|(_, idx)| idx).collect();
+        let max_day = scenario
+            .traffic_weekday_weights
+            .iter()
+            .chain(std::iter::once(&scenario.traffic_holiday_weight))
+            .max()
+            .unwrap();
+        let max_hour = scenario.traffic_hour_weights.iter().max().unwrap();
+        TrafficModel {
+            zipf: Zipf::new(numbers as u64, scenario.traffic_zipf_exponent)
+                .expect("Invalid traffic_zipf_exponent"),
+            ranks,
+            duration: LogNormal::new(
+                scenario.call_duration_median_seconds.ln(),
+                scenario.call_duration_sigma,
+            )
+            .expect("Invalid call duration parameters"),
+            dispositions: WeightedIndex::new(&scenario.call_disposition_weights)
+                .expect("Invalid call_disposition_weights"),
+            hour_weights: scenario.traffic_hour_weights.clone(),
+            weekday_weights: scenario.traffic_weekday_weights.clone(),
+            holiday_weight: scenario.traffic_holiday_weight,
+            max_weight: (max_day * max_hour).max(1),
+        }
+    }
+
+    /// Index of the number placing the next call.
+    pub fn pick_number(&self) -> usize {
+        let rank = self.zipf.sample(&mut rand::thread_rng()) as usize;
+        self.ranks[rank - 1]
+    }
+
+    fn weight(&self, date: DateTime<Utc>) -> u32 {
+        // Czech local time, daylight saving is not worth the precision here.
+        let local = date.with_timezone(&FixedOffset::east(3600));
+        let day = if is_czech_holiday(local.date().naive_local()) {
+            self.holiday_weight
+        } else {
+            self.weekday_weights[local.weekday().num_days_from_monday() as usize]
+        };
+        day * self.hour_weights[local.hour() as usize]
+    }
+
+    /// Start of a call within the lifespan, drawn by rejection sampling
+    /// against the weekly profile.
+    pub fn call_date(&self, lifespan: &Lifespan) -> DateTime<Utc> {
+        let mut rng = rand::thread_rng();
+        let mut date = date_within(lifespan);
+        for _ in 0..100 {
+            if rng.gen_range(0..self.max_weight) < self.weight(date) {
+                break;
+            }
+            date = date_within(lifespan);
+        }
+        date
+    }
+
+    /// Talk time of an answered call in seconds, at least one.
+    fn duration(&self) -> u16 {
+        let seconds = self.duration.sample(&mut rand::thread_rng());
+        seconds.clamp(1.0, MAX_CALL_SECONDS).round() as u16
+    }
+
+    /// Disposition of a call attempt with its ringing and billable seconds.
+    /// Unanswered calls ring until the caller gives up, busy, failed and
+    /// congested ones end within a few seconds.
+    pub fn outcome(&self) -> CallOutcome {
+        let mut rng = rand::thread_rng();
+        let disposition = DISPOSITIONS[self.dispositions.sample(&mut rng)];
+        let (ring_duration, billsec) = match disposition {
+            DISPOSITION_ANSWERED => (rng.gen_range(1..=30), self.duration()),
+            DISPOSITION_NO_ANSWER => (rng.gen_range(15..=60), 0),
+            DISPOSITION_BUSY => (rng.gen_range(0..=5), 0),
+            _ => (rng.gen_range(0..=3), 0),
+        };
+        CallOutcome {
+            disposition,
+            ring_duration,
+            billsec,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easter_sunday_matches_known_dates() {
+        for (year, month, day) in [
+            (1818, 3, 22),
+            (1943, 4, 25),
+            (2000, 4, 23),
+            (2008, 3, 23),
+            (2019, 4, 21),
+            (2024, 3, 31),
+            (2025, 4, 20),
+            (2038, 4, 25),
+        ] {
+            assert_eq!(
+                easter_sunday(year),
+                NaiveDate::from_ymd(year, month, day),
+                "{}",
+                year
+            );
+        }
+    }
+
+    #[test]
+    fn easter_holidays_are_recognised() {
+        assert!(is_czech_holiday(NaiveDate::from_ymd(2024, 3, 29)));
+        assert!(is_czech_holiday(NaiveDate::from_ymd(2024, 4, 1)));
+        assert!(!is_czech_holiday(NaiveDate::from_ymd(2024, 3, 31)));
+        assert!(!is_czech_holiday(NaiveDate::from_ymd(2024, 4, 21)));
+    }
+}
@@ -0,0 +1,20 @@
+use crate::entities::credentials::TestCredential;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Writes `credentials.csv` with the plaintext passwords of the accounts
+/// picked for login and SIP registration tests.
+pub fn write_credentials(dir: &Path, credentials: &[TestCredential]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut file = io::BufWriter::new(fs::File::create(dir.join("credentials.csv"))?);
+    writeln!(file, "kind,id,username,password")?;
+    for credential in credentials {
+        writeln!(
+            file,
+            "{},{},{},{}",
+            credential.kind, credential.id, credential.username, credential.password
+        )?;
+    }
+    file.flush()
+}
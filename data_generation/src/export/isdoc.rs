@@ -0,0 +1,412 @@
+use super::*;
+use crate::entities::*;
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const ISDOC_NAMESPACE: &str = "http://isdoc.cz/namespace/2013";
+const ISDOC_VERSION: &str = "6.0.2";
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn uuid() -> String {
+    use fake::Fake;
+    let hex: String =
+        fake::StringFaker::with(String::from("0123456789ABCDEF").into_bytes(), 32..33).fake();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+struct Party<'a> {
+    user_id: &'a str,
+    id: &'a str,
+    name: &'a str,
+    street: &'a str,
+    building: &'a str,
+    city: &'a str,
+    zip: &'a str,
+    /// ISO 3166 country code.
+    country: &'a str,
+    vat_id: Option<&'a str>,
+}
+
+/// Czech name of the country, the code itself for countries not listed.
+fn country_name(code: &str) -> &str {
+    match code {
+        "CZ" => "Česká republika",
+        "SK" => "Slovensko",
+        "DE" => "Německo",
+        "AT" => "Rakousko",
+        "PL" => "Polsko",
+        "US" => "Spojené státy americké",
+        _ => code,
+    }
+}
+
+fn write_party(xml: &mut String, party: &Party) {
+    let _ = write!(
+        xml,
+        "<Party>\
+            <PartyIdentification><UserID>{user_id}</UserID><ID>{id}</ID></PartyIdentification>\
+            <PartyName><Name>{name}</Name></PartyName>\
+            <PostalAddress>\
+                <StreetName>{street}</StreetName>\
+                <BuildingNumber>{building}</BuildingNumber>\
+                <CityName>{city}</CityName>\
+                <PostalZone>{zip}</PostalZone>\
+                <Country><IdentificationCode>{country}</IdentificationCode><Name>{country_name}</Name></Country>\
+            </PostalAddress>",
+        user_id = escape(party.user_id),
+        id = escape(party.id),
+        name = escape(party.name),
+        street = escape(party.street),
+        building = escape(party.building),
+        city = escape(party.city),
+        zip = escape(party.zip),
+        country = escape(party.country),
+        country_name = escape(country_name(party.country)),
+    );
+    if let Some(vat_id) = party.vat_id {
+        let _ = write!(
+            xml,
+            "<PartyTaxScheme><CompanyID>{}</CompanyID><TaxScheme>VAT</TaxScheme></PartyTaxScheme>",
+            escape(vat_id)
+        );
+    }
+    xml.push_str("</Party>");
+}
+
+/// Renders one invoice as an ISDOC 6.0.2 document. Totals are summed from the
+/// `lines`, VAT is computed per line with the invoice tax rate.
+pub fn to_isdoc(
+    invoice: &Invoice,
+    lines: &[&InvoiceHasItems],
+    items: &HashMap<u32, &InvoiceItem>,
+    contract: &Contract,
+    address: Option<&Address>,
+) -> String {
+    let percent = Decimal::from(invoice.tax_value_percent);
+    let issued = parse_date(&invoice.created_at).format("%F").to_string();
+    let taxable = parse_date(&invoice.taxable_period).format("%F").to_string();
+    let due = parse_date(&invoice.maturity).format("%F").to_string();
+
+    let mut xml = String::with_capacity(4096);
+    let _ = write!(
+        xml,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <Invoice xmlns=\"{ns}\" version=\"{ver}\">\
+            <DocumentType>1</DocumentType>\
+            <ID>{id}</ID>\
+            <UUID>{uuid}</UUID>\
+            <IssueDate>{issued}</IssueDate>\
+            <TaxPointDate>{taxable}</TaxPointDate>\
+            <VATApplicable>true</VATApplicable>\
+            <ElectronicPossibilityAgreementReference></ElectronicPossibilityAgreementReference>\
+            <LocalCurrencyCode>{cur}</LocalCurrencyCode>\
+            <CurrRate>1</CurrRate>\
+            <RefCurrRate>1</RefCurrRate>\
+            <AccountingSupplierParty>",
+        ns = ISDOC_NAMESPACE,
+        ver = ISDOC_VERSION,
+        id = invoice.invoice_number,
+        uuid = uuid(),
+        issued = issued,
+        taxable = taxable,
+        cur = CURRENCY,
+    );
+    write_party(
+        &mut xml,
+        &Party {
+            user_id: "",
+            id: SUPPLIER_ID,
+            name: SUPPLIER_NAME,
+            street: SUPPLIER_STREET,
+            building: SUPPLIER_BUILDING,
+            city: SUPPLIER_CITY,
+            zip: SUPPLIER_ZIP,
+            country: "CZ",
+            vat_id: Some(SUPPLIER_VAT_ID),
+        },
+    );
+    xml.push_str("</AccountingSupplierParty><AccountingCustomerParty>");
+    let (street, building, city, zip) = match address {
+        Some(a) => (
+            a.street_name.clone(),
+            a.house_number.to_string(),
+            a.city.clone(),
+            format!("{:05}", a.zip_code),
+        ),
+        None => (String::new(), String::new(), String::new(), String::new()),
+    };
+    let user_id = contract.contract_id.unwrap_or_default().to_string();
+    let id = contract
+        .identification_number
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+    write_party(
+        &mut xml,
+        &Party {
+            user_id: &user_id,
+            id: &id,
+            name: &contract.contract_name,
+            street: &street,
+            building: &building,
+            city: &city,
+            zip: &zip,
+            country: contract.locale.rsplit('_').next().unwrap_or("CZ"),
+            vat_id: contract.vat_identification_number.as_deref(),
+        },
+    );
+    xml.push_str("</AccountingCustomerParty><InvoiceLines>");
+
+    for (idx, line) in lines.iter().enumerate() {
+        let LineAmounts { unit, amount, tax } = line_amounts(line, invoice.tax_value_percent);
+        let unit_tax_inclusive = (unit + unit * percent / Decimal::from(100)).round_dp(2);
+        let name = items
+            .get(&line.invoice_item_id)
+            .map(|i| i.item_name.as_str())
+            .unwrap_or_default();
+        let _ = write!(
+            xml,
+            "<InvoiceLine>\
+                <ID>{id}</ID>\
+                <InvoicedQuantity unitCode=\"ks\">{count}</InvoicedQuantity>\
+                <LineExtensionAmount>{amount}</LineExtensionAmount>\
+                <LineExtensionAmountTaxInclusive>{amount_inc}</LineExtensionAmountTaxInclusive>\
+                <LineExtensionTaxAmount>{tax}</LineExtensionTaxAmount>\
+                <UnitPrice>{unit}</UnitPrice>\
+                <UnitPriceTaxInclusive>{unit_inc}</UnitPriceTaxInclusive>\
+                <ClassifiedTaxCategory>\
+                    <Percent>{percent}</Percent>\
+                    <VATCalculationMethod>0</VATCalculationMethod>\
+                </ClassifiedTaxCategory>\
+                <Item><Description>{name}</Description></Item>\
+            </InvoiceLine>",
+            id = idx + 1,
+            count = line.item_count,
+            amount = amount,
+            amount_inc = amount + tax,
+            tax = tax,
+            unit = unit,
+            unit_inc = unit_tax_inclusive,
+            percent = percent,
+            name = escape(name),
+        );
+    }
+
+    let (taxable_total, tax_total) = invoice_totals(lines, invoice.tax_value_percent);
+    let total = taxable_total + tax_total;
+    let _ = writeln!(
+        xml,
+        "</InvoiceLines>\
+        <TaxTotal>\
+            <TaxSubTotal>\
+                <TaxableAmount>{taxable}</TaxableAmount>\
+                <TaxAmount>{tax}</TaxAmount>\
+                <TaxInclusiveAmount>{total}</TaxInclusiveAmount>\
+                <AlreadyClaimedTaxableAmount>0</AlreadyClaimedTaxableAmount>\
+                <AlreadyClaimedTaxAmount>0</AlreadyClaimedTaxAmount>\
+                <AlreadyClaimedTaxInclusiveAmount>0</AlreadyClaimedTaxInclusiveAmount>\
+                <DifferenceTaxableAmount>{taxable}</DifferenceTaxableAmount>\
+                <DifferenceTaxAmount>{tax}</DifferenceTaxAmount>\
+                <DifferenceTaxInclusiveAmount>{total}</DifferenceTaxInclusiveAmount>\
+                <TaxCategory><Percent>{percent}</Percent></TaxCategory>\
+            </TaxSubTotal>\
+            <TaxAmount>{tax}</TaxAmount>\
+        </TaxTotal>\
+        <LegalMonetaryTotal>\
+            <TaxExclusiveAmount>{taxable}</TaxExclusiveAmount>\
+            <TaxInclusiveAmount>{total}</TaxInclusiveAmount>\
+            <AlreadyClaimedTaxExclusiveAmount>0</AlreadyClaimedTaxExclusiveAmount>\
+            <AlreadyClaimedTaxInclusiveAmount>0</AlreadyClaimedTaxInclusiveAmount>\
+            <DifferenceTaxExclusiveAmount>{taxable}</DifferenceTaxExclusiveAmount>\
+            <DifferenceTaxInclusiveAmount>{total}</DifferenceTaxInclusiveAmount>\
+            <PaidDepositsAmount>0</PaidDepositsAmount>\
+            <PayableAmount>{total}</PayableAmount>\
+        </LegalMonetaryTotal>\
+        <PaymentMeans>\
+            <Payment>\
+                <PaidAmount>{total}</PaidAmount>\
+                <PaymentMeansCode>42</PaymentMeansCode>\
+                <Details>\
+                    <PaymentDueDate>{due}</PaymentDueDate>\
+                    <ID>{account}</ID>\
+                    <BankCode>{bank}</BankCode>\
+                    <Name>{bank_name}</Name>\
+                    <IBAN>{iban}</IBAN>\
+                    <BIC>{bic}</BIC>\
+                    <VariableSymbol>{vs}</VariableSymbol>\
+                </Details>\
+            </Payment>\
+        </PaymentMeans>\
+        </Invoice>",
+        taxable = taxable_total,
+        tax = tax_total,
+        total = total,
+        percent = percent,
+        due = due,
+        account = SUPPLIER_ACCOUNT,
+        bank = SUPPLIER_BANK_CODE,
+        bank_name = escape(SUPPLIER_BANK_NAME),
+        iban = SUPPLIER_IBAN,
+        bic = SUPPLIER_BIC,
+        vs = contract.variable_symbol,
+    );
+    xml
+}
+
+/// Writes `{invoice_number}.isdoc` for every invoice into `dir`.
+pub fn write_isdoc(
+    dir: &Path,
+    invoices: &[Invoice],
+    invoice_lines: &[InvoiceHasItems],
+    invoice_items: &[InvoiceItem],
+    contracts: &[Contract],
+    addresses: &[Address],
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let items: HashMap<u32, &InvoiceItem> = invoice_items
+        .iter()
+        .filter_map(|i| i.item_id.map(|id| (id, i)))
+        .collect();
+    let contracts: HashMap<u32, &Contract> = contracts
+        .iter()
+        .filter_map(|c| c.contract_id.map(|id| (id, c)))
+        .collect();
+    let addresses: HashMap<u32, &Address> = addresses.iter().map(|a| (a.contract_id, a)).collect();
+    let lines = group_lines(invoice_lines);
+
+    for invoice in invoices {
+        let contract = match contracts.get(&invoice.contract_id) {
+            Some(c) => c,
+            None => continue,
+        };
+        let xml = to_isdoc(
+            invoice,
+            lines
+                .get(&invoice.invoice_number)
+                .map(|l| &l[..])
+                .unwrap_or_default(),
+            &items,
+            contract,
+            addresses.get(&invoice.contract_id).copied(),
+        );
+        fs::write(dir.join(format!("{}.isdoc", invoice.invoice_number)), xml)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn between<'a>(xml: &'a str, open: &str, close: &str) -> &'a str {
+        let start = xml.find(open).expect("Missing element") + open.len();
+        let end = start + xml[start..].find(close).expect("Unclosed element");
+        &xml[start..end]
+    }
+
+    fn amount(xml: &str, tag: &str) -> Decimal {
+        between(xml, tag, "<").parse().expect("Invalid amount")
+    }
+
+    #[test]
+    fn credit_line_lowers_totals_and_keeps_element_order() {
+        let invoice = Invoice::new(
+            2_024_000_001,
+            0.0,
+            21,
+            String::from("2024-03-01T00:00:00+00:00"),
+            String::from("2024-02-29T00:00:00+00:00"),
+            String::from("2024-03-15T00:00:00+00:00"),
+            None,
+            7,
+        );
+        let service = InvoiceHasItems::new(invoice.invoice_number, 1, 100.0, 2);
+        let credit = InvoiceHasItems::new(invoice.invoice_number, 2, -50.0, 1);
+        let service_item = InvoiceItem::new(Some(1), String::from("Paušál"), 100.0);
+        let credit_item = InvoiceItem::new(Some(2), String::from("Dobropis <sleva>"), -50.0);
+        let items: HashMap<u32, &InvoiceItem> = vec![(1, &service_item), (2, &credit_item)]
+            .into_iter()
+            .collect();
+        let contract = Contract::new(
+            7,
+            String::from("Novák & syn"),
+            1234,
+            String::from("info@novak.cz"),
+            "+420601123456".parse().unwrap(),
+            None,
+            None,
+            Some(12_345_678),
+            None,
+        );
+
+        let xml = to_isdoc(&invoice, &[&service, &credit], &items, &contract, None);
+
+        let order = [
+            "<DocumentType>",
+            "<ID>",
+            "<UUID>",
+            "<IssueDate>",
+            "<TaxPointDate>",
+            "<VATApplicable>",
+            "<LocalCurrencyCode>",
+            "<AccountingSupplierParty>",
+            "<AccountingCustomerParty>",
+            "<InvoiceLines>",
+            "<TaxTotal>",
+            "<LegalMonetaryTotal>",
+            "<PaymentMeans>",
+        ];
+        let positions: Vec<usize> = order
+            .iter()
+            .map(|tag| xml.find(tag).expect("Missing element"))
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(xml.matches("<InvoiceLine>").count(), 2);
+        let lines = between(&xml, "<InvoiceLines>", "</InvoiceLines>");
+        let credit_line = &lines[lines.rfind("<InvoiceLine>").unwrap()..];
+        assert_eq!(
+            amount(credit_line, "<LineExtensionAmount>"),
+            Decimal::new(-5000, 2)
+        );
+        assert_eq!(
+            amount(credit_line, "<LineExtensionTaxAmount>"),
+            Decimal::new(-1050, 2)
+        );
+        assert!(credit_line.contains("Dobropis &lt;sleva&gt;"));
+
+        let totals = between(&xml, "<LegalMonetaryTotal>", "</LegalMonetaryTotal>");
+        assert_eq!(
+            amount(totals, "<TaxExclusiveAmount>"),
+            Decimal::new(15000, 2)
+        );
+        assert_eq!(
+            amount(totals, "<TaxInclusiveAmount>"),
+            Decimal::new(18150, 2)
+        );
+        assert_eq!(amount(totals, "<PayableAmount>"), Decimal::new(18150, 2));
+        assert_eq!(
+            amount(&xml, "<TaxSubTotal><TaxableAmount>"),
+            Decimal::new(15000, 2)
+        );
+        assert!(xml.contains("<Name>Novák &amp; syn</Name>"));
+    }
+}
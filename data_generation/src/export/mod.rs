@@ -0,0 +1,60 @@
+use crate::entities::InvoiceHasItems;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+pub mod credentials;
+pub mod isdoc;
+pub mod number_plan;
+pub mod spayd;
+
+// Operator issuing all generated invoices and receiving the payments.
+pub const SUPPLIER_NAME: &str = "Dais VoIP s.r.o.";
+pub const SUPPLIER_ID: &str = "27082440";
+pub const SUPPLIER_VAT_ID: &str = "CZ27082440";
+pub const SUPPLIER_STREET: &str = "17. listopadu";
+pub const SUPPLIER_BUILDING: &str = "2172";
+pub const SUPPLIER_CITY: &str = "Ostrava";
+pub const SUPPLIER_ZIP: &str = "70800";
+pub const SUPPLIER_ACCOUNT: &str = "19-2000145399";
+pub const SUPPLIER_BANK_CODE: &str = "0800";
+pub const SUPPLIER_BANK_NAME: &str = "Česká spořitelna, a.s.";
+pub const SUPPLIER_IBAN: &str = "CZ6508000000192000145399";
+pub const SUPPLIER_BIC: &str = "GIBACZPX";
+pub const CURRENCY: &str = "CZK";
+
+pub fn money(amount: f32) -> Decimal {
+    Decimal::from_f32(amount).unwrap_or_default().round_dp(2)
+}
+
+pub struct LineAmounts {
+    pub unit: Decimal,
+    pub amount: Decimal,
+    pub tax: Decimal,
+}
+
+/// Tax exclusive unit price, line amount and VAT of a single invoice line.
+pub fn line_amounts(line: &InvoiceHasItems, tax_percent: u8) -> LineAmounts {
+    let unit = money(line.item_unit_cost);
+    let amount = unit * Decimal::from(line.item_count);
+    let tax = (amount * Decimal::from(tax_percent) / Decimal::from(100)).round_dp(2);
+    LineAmounts { unit, amount, tax }
+}
+
+/// Sum of tax exclusive amounts and VAT over all invoice lines.
+pub fn invoice_totals(lines: &[&InvoiceHasItems], tax_percent: u8) -> (Decimal, Decimal) {
+    lines
+        .iter()
+        .map(|l| line_amounts(l, tax_percent))
+        .fold((Decimal::ZERO, Decimal::ZERO), |(amount, tax), l| {
+            (amount + l.amount, tax + l.tax)
+        })
+}
+
+pub fn group_lines(lines: &[InvoiceHasItems]) -> HashMap<u64, Vec<&InvoiceHasItems>> {
+    let mut grouped: HashMap<u64, Vec<&InvoiceHasItems>> = HashMap::new();
+    for line in lines {
+        grouped.entry(line.invoice_number).or_default().push(line);
+    }
+    grouped
+}
@@ -0,0 +1,30 @@
+use crate::entities::number_plan::NumberPlan;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Writes `number_plan.csv` listing the configured number ranges and every
+/// DID block with the contract it was assigned to.
+pub fn write_number_plan(dir: &Path, plan: &NumberPlan) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut file = io::BufWriter::new(fs::File::create(dir.join("number_plan.csv"))?);
+    writeln!(file, "kind,phone_country_code,first,last,contract_id")?;
+    for range in plan.ranges.iter() {
+        writeln!(
+            file,
+            "RANGE,{},{},{},",
+            range.phone_country_code, range.first, range.last
+        )?;
+    }
+    for block in plan.blocks.iter() {
+        writeln!(
+            file,
+            "DID_BLOCK,{},{},{},{}",
+            block.phone_country_code,
+            block.first,
+            block.first + u64::from(block.size) - 1,
+            block.contract_id
+        )?;
+    }
+    file.flush()
+}
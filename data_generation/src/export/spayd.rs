@@ -0,0 +1,250 @@
+use super::*;
+use crate::entities::*;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+#[cfg(test)]
+use std::str::FromStr;
+
+const SPAYD_VERSION: &str = "1.0";
+
+/// Czech "QR Platba" payment payload (Short Payment Descriptor).
+#[derive(Debug, PartialEq)]
+pub struct Spayd {
+    pub iban: String,
+    pub bic: Option<String>,
+    pub amount: Decimal,
+    pub currency: String,
+    pub variable_symbol: String,
+    pub due_date: NaiveDate,
+    pub message: Option<String>,
+}
+
+impl Spayd {
+    pub fn for_invoice(
+        invoice: &Invoice,
+        lines: &[&InvoiceHasItems],
+        contract: &Contract,
+    ) -> Spayd {
+        let (amount, tax) = invoice_totals(lines, invoice.tax_value_percent);
+        Spayd {
+            iban: SUPPLIER_IBAN.to_string(),
+            bic: Some(SUPPLIER_BIC.to_string()),
+            amount: amount + tax,
+            currency: CURRENCY.to_string(),
+            variable_symbol: contract.variable_symbol.to_string(),
+            due_date: parse_date(&invoice.maturity).naive_local().date(),
+            message: Some(format!("Faktura {}", invoice.invoice_number)),
+        }
+    }
+
+    /// Reads a payload back, only needed to check the encoding.
+    #[cfg(test)]
+    pub fn parse(payload: &str) -> Result<Spayd, String> {
+        let mut parts = payload.split('*');
+        if parts.next() != Some("SPD") {
+            return Err("Missing SPD header".to_string());
+        }
+        if parts.next() != Some(SPAYD_VERSION) {
+            return Err("Unsupported SPAYD version".to_string());
+        }
+
+        let mut fields: HashMap<&str, String> = HashMap::new();
+        for part in parts.filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once(':')
+                .ok_or_else(|| format!("Malformed field {}", part))?;
+            fields.insert(key, decode(value)?);
+        }
+        let mut field = |key: &str| {
+            fields
+                .remove(key)
+                .ok_or_else(|| format!("Missing field {}", key))
+        };
+
+        let account = field("ACC")?;
+        let (iban, bic) = match account.split_once('+') {
+            Some((iban, bic)) => (iban.to_string(), Some(bic.to_string())),
+            None => (account, None),
+        };
+        Ok(Spayd {
+            iban,
+            bic,
+            amount: Decimal::from_str(&field("AM")?).map_err(|e| e.to_string())?,
+            currency: field("CC")?,
+            variable_symbol: field("X-VS")?,
+            due_date: NaiveDate::parse_from_str(&field("DT")?, "%Y%m%d")
+                .map_err(|e| e.to_string())?,
+            message: field("MSG").ok(),
+        })
+    }
+}
+
+fn encode(value: &str) -> String {
+    value.replace('%', "%25").replace('*', "%2A")
+}
+
+#[cfg(test)]
+fn decode(value: &str) -> Result<String, String> {
+    let mut decoded = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            decoded.push(c);
+            continue;
+        }
+        let hex: String = chars.by_ref().take(2).collect();
+        let byte = u8::from_str_radix(&hex, 16).map_err(|_| format!("Bad escape %{}", hex))?;
+        decoded.push(byte as char);
+    }
+    Ok(decoded)
+}
+
+impl fmt::Display for Spayd {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "SPD*{}*ACC:{}", SPAYD_VERSION, encode(&self.iban))?;
+        if let Some(bic) = &self.bic {
+            write!(fmt, "+{}", encode(bic))?;
+        }
+        write!(
+            fmt,
+            "*AM:{:.2}*CC:{}*DT:{}*X-VS:{}",
+            self.amount,
+            encode(&self.currency),
+            self.due_date.format("%Y%m%d"),
+            encode(&self.variable_symbol),
+        )?;
+        if let Some(message) = &self.message {
+            write!(fmt, "*MSG:{}", encode(message))?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `spayd.csv` with the QR payment payload of every invoice into `dir`.
+pub fn write_spayd(
+    dir: &Path,
+    invoices: &[Invoice],
+    invoice_lines: &[InvoiceHasItems],
+    contracts: &[Contract],
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let contracts: HashMap<u32, &Contract> = contracts
+        .iter()
+        .filter_map(|c| c.contract_id.map(|id| (id, c)))
+        .collect();
+    let lines = group_lines(invoice_lines);
+
+    let mut file = io::BufWriter::new(fs::File::create(dir.join("spayd.csv"))?);
+    writeln!(file, "invoice_number,contract_id,spayd")?;
+    for invoice in invoices {
+        let contract = match contracts.get(&invoice.contract_id) {
+            Some(c) => c,
+            None => continue,
+        };
+        let spayd = Spayd::for_invoice(
+            invoice,
+            lines
+                .get(&invoice.invoice_number)
+                .map(|l| &l[..])
+                .unwrap_or_default(),
+            contract,
+        );
+        if spayd.amount <= Decimal::ZERO {
+            continue;
+        }
+        writeln!(
+            file,
+            "{},{},{}",
+            invoice.invoice_number, invoice.contract_id, spayd
+        )?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment() -> Spayd {
+        Spayd {
+            iban: SUPPLIER_IBAN.to_string(),
+            bic: Some(SUPPLIER_BIC.to_string()),
+            amount: Decimal::new(18150, 2),
+            currency: CURRENCY.to_string(),
+            variable_symbol: String::from("1234"),
+            due_date: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            message: Some(String::from("Faktura 2024000001")),
+        }
+    }
+
+    #[test]
+    fn invoice_payload_round_trips() {
+        let invoice = Invoice::new(
+            2_024_000_001,
+            0.0,
+            21,
+            String::from("2024-03-01T00:00:00+00:00"),
+            String::from("2024-02-29T00:00:00+00:00"),
+            String::from("2024-03-15T00:00:00+00:00"),
+            None,
+            7,
+        );
+        let line = InvoiceHasItems::new(invoice.invoice_number, 1, 150.0, 1);
+        let contract = Contract::new(
+            7,
+            String::from("Novák"),
+            1234,
+            String::from("jan.novak@seznam.cz"),
+            "+420601123456".parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        );
+        let spayd = Spayd::for_invoice(&invoice, &[&line], &contract);
+        let payload = spayd.to_string();
+        assert_eq!(
+            payload,
+            "SPD*1.0*ACC:CZ6508000000192000145399+GIBACZPX*AM:181.50*CC:CZK\
+            *DT:20240315*X-VS:1234*MSG:Faktura 2024000001"
+        );
+        assert_eq!(Spayd::parse(&payload), Ok(spayd));
+    }
+
+    #[test]
+    fn message_escapes_round_trip() {
+        let spayd = Spayd {
+            message: Some(String::from("Sleva 10% *akce*")),
+            ..payment()
+        };
+        let payload = spayd.to_string();
+        assert!(payload.ends_with("*MSG:Sleva 10%25 %2Aakce%2A"));
+        assert_eq!(Spayd::parse(&payload), Ok(spayd));
+    }
+
+    #[test]
+    fn account_without_bic_round_trips() {
+        let spayd = Spayd {
+            bic: None,
+            message: None,
+            ..payment()
+        };
+        let payload = spayd.to_string();
+        assert!(payload.starts_with("SPD*1.0*ACC:CZ6508000000192000145399*AM:"));
+        assert_eq!(Spayd::parse(&payload), Ok(spayd));
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        let payload = payment().to_string();
+        assert!(Spayd::parse(&payload.replace("*CC:", "*CC")).is_err());
+        assert!(Spayd::parse(&payload.replace("*X-VS:1234", "")).is_err());
+        assert!(Spayd::parse(&payload.replace("Faktura", "Fa%ZZktura")).is_err());
+        assert!(Spayd::parse(&payload.replace("SPD*1.0", "SPD*2.0")).is_err());
+        assert!(Spayd::parse(&payload.replace("AM:181.50", "AM:abc")).is_err());
+    }
+}
@@ -1,11 +1,25 @@
+mod config;
 mod entities;
+mod export;
+mod simulation;
+use config::{Scenario, Segment};
+use entities::credentials::Credentials;
+use entities::dictionary::Dictionary;
 use entities::generators::*;
+use entities::identity::Identities;
+use entities::number_plan::NumberPlan;
+use entities::traffic::TrafficModel;
 use entities::*;
-use fake::{Fake, StringFaker};
+use fake::Fake;
 use postgres::{Client, Config, NoTls};
+use rand::distributions::WeightedIndex;
+use rand_distr::Distribution;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::env;
 use std::io::Write;
+use std::path::Path;
 use std::vec::Vec;
 
 fn insert_with_copy<T>(cfg: &Config, collection: &Vec<T>) -> ()
@@ -86,6 +100,74 @@ fn get_last_identities(cfg: &Config) -> (u32, u32, u32, u32, u32, u32, u32, i32)
     )
 }
 
+fn create_table<T: CreatesTable>(cfg: &Config) {
+    let mut client = cfg
+        .connect(NoTls)
+        .expect("Unable to connect to remote host");
+    client
+        .execute(T::create_table(), &[])
+        .expect("Failed to create table");
+}
+
+fn alter_table<T: AltersTable>(cfg: &Config) {
+    let mut client = cfg
+        .connect(NoTls)
+        .expect("Unable to connect to remote host");
+    for q in T::alter_table().split(';') {
+        client.execute(q, &[]).expect("Failed to alter table");
+    }
+}
+
+fn get_last_id(cfg: &Config, table: &str, column: &str) -> u32 {
+    let mut client = cfg.connect(NoTls).expect("Failed joining to postgres");
+    let id: i64 = client
+        .query_one(
+            &format!("select coalesce(max({}), 0)::bigint from {}", column, table)[..],
+            &[],
+        )
+        .expect("Failed to get last id value")
+        .get(0);
+    id.try_into().unwrap()
+}
+
+fn load_number_plan(cfg: &Config, scenario: &Scenario) -> NumberPlan {
+    let mut client = cfg.connect(NoTls).expect("Failed joining to postgres");
+    let mut plan = NumberPlan::new(scenario.number_ranges.clone());
+    for row in client
+        .query(
+            "select phone_country_code::integer, number::text from voip_number",
+            &[],
+        )
+        .expect("Failed to load existing numbers")
+    {
+        let pcc: i32 = row.get(0);
+        let number: String = row.get(1);
+        if let Ok(number) = format!("+{}{}", pcc, number).parse::<PhoneNumber>() {
+            plan.reserve(number);
+        }
+    }
+    plan
+}
+
+/// Identity generator aware of the e-mails and logins already in the database.
+fn load_identities(cfg: &Config, scenario: &Scenario) -> Identities {
+    let mut client = cfg.connect(NoTls).expect("Failed joining to postgres");
+    let mut identities = Identities::new(&scenario.email_domains);
+    for row in client
+        .query("select email from contract where email is not null", &[])
+        .expect("Failed to load existing emails")
+    {
+        identities.reserve_email(row.get(0));
+    }
+    for row in client
+        .query("select login from participant where login is not null", &[])
+        .expect("Failed to load existing logins")
+    {
+        identities.reserve_login(row.get(0));
+    }
+    identities
+}
+
 fn drop_primary_keys(cfg: &Config) {
     let mut client = cfg
         .connect(NoTls)
@@ -154,7 +236,7 @@ fn main() -> () {
     let args: Vec<String> = env::args().collect();
     if args.len() <= 6 {
         println!(
-            "Please provide arguments in form of {{hostname}} {{user}} {{password}} {{dbname}} {{contract_count}} {{calls_count}}"
+            "Please provide arguments in form of {{hostname}} {{user}} {{password}} {{dbname}} {{contract_count}} {{calls_count}} [{{export_dir}}] [{{scenario_file}}]"
         );
         return ();
     }
@@ -165,6 +247,11 @@ fn main() -> () {
     let db_name = &args[4];
     let contracts_total: usize = args[5].parse::<usize>().unwrap();
     let calls_count: usize = args[6].parse::<usize>().unwrap();
+    let export_dir = args.get(7).filter(|dir| !dir.is_empty());
+    let scenario = match args.get(8) {
+        Some(path) => Scenario::load(path),
+        None => Scenario::default(),
+    };
     let mut cfg: Config = Client::configure();
     cfg.host(db_hostname);
     cfg.user(db_user);
@@ -176,17 +263,159 @@ fn main() -> () {
 
     let mut in_num = in_num as i64;
 
+    alter_table::<Participant>(&cfg);
+    let mut identities = load_identities(&cfg, &scenario);
+
+    let dictionaries: HashMap<String, Dictionary> = scenario
+        .locales
+        .iter()
+        .map(|share| {
+            let dictionary =
+                Dictionary::for_locale(&share.locale, scenario.dictionary_dir.as_deref());
+            (share.locale.clone(), dictionary)
+        })
+        .collect();
+    for dictionary in dictionaries.values() {
+        assert!(
+            scenario
+                .number_ranges
+                .iter()
+                .any(|r| r.phone_country_code == dictionary.phone_country_code),
+            "Locale {} needs a number range for +{}",
+            dictionary.locale,
+            dictionary.phone_country_code
+        );
+    }
+    let locale_index = WeightedIndex::new(scenario.locales.iter().map(|share| share.weight))
+        .expect("Invalid locale weights");
+    let segments: HashMap<&str, &Segment> =
+        scenario.segments.iter().map(|s| (&s.name[..], s)).collect();
+    let segment_index = WeightedIndex::new(scenario.segments.iter().map(|s| s.share))
+        .expect("Invalid segment shares");
+
     let mut vs_symbol = 100_000;
     let mut contracts: Vec<Contract> = Vec::<Contract>::with_capacity(contracts_total);
     for _ in 1..=contracts_total {
         vs_symbol += 1;
         cid += 1;
-        contracts.push(gen_contract(cid, vs_symbol));
+        let locale = &scenario.locales[locale_index.sample(&mut rand::thread_rng())].locale;
+        contracts.push(gen_contract(
+            cid,
+            vs_symbol,
+            scenario.contract_churn_percent,
+            &dictionaries[locale],
+            &scenario.segments[segment_index.sample(&mut rand::thread_rng())],
+            &mut identities,
+        ));
+    }
+
+    let mut i_items: Vec<InvoiceItem> = Vec::<InvoiceItem>::with_capacity(10);
+    i_items.push(gen_invoice_item(iid + 1, String::from("Calls")));
+    i_items.push(gen_invoice_item(iid + 2, String::from("Phone 3CX")));
+    i_items.push(gen_invoice_item(iid + 3, String::from("Phone 4G")));
+    i_items.push(gen_invoice_item(iid + 4, String::from("Phone 10L")));
+    i_items.push(gen_invoice_item(iid + 5, String::from("Phone cable")));
+    i_items.push(gen_invoice_item(iid + 6, String::from("Phone 787FU")));
+    i_items.push(InvoiceItem::new(
+        Some(iid + 7),
+        String::from("Bonus credit"),
+        0f32,
+    ));
+
+    let mut invoices: Vec<Invoice> = Vec::<Invoice>::with_capacity(contracts_total * 3);
+    let mut iih: Vec<InvoiceHasItems> = Vec::<InvoiceHasItems>::with_capacity(contracts_total * 5);
+
+    println!("Generating invoices");
+    for c in contracts.iter() {
+        let lifespan = c.lifespan();
+        let quantity = &segments[&c.segment[..]].invoice_quantity;
+        let invoices_count = (0..8).fake::<u8>();
+        for _ in 1..invoices_count {
+            in_num += 1;
+            let items_count = (2..4).fake::<u8>();
+            let mut total_price = 0f32;
+            let mut picked_items: Vec<usize> = vec![];
+            for _i in 1..items_count {
+                let mut rnd_item: usize;
+                loop {
+                    rnd_item = (0..6).fake::<usize>();
+
+                    if !picked_items.contains(&rnd_item) {
+                        break;
+                    }
+                }
+
+                picked_items.push(rnd_item);
+
+                let count = gen_count(quantity);
+                total_price += i_items[rnd_item].unit_cost * count as f32;
+
+                iih.push(InvoiceHasItems::new(
+                    in_num.unsigned_abs(),
+                    i_items[rnd_item].item_id.unwrap(),
+                    i_items[rnd_item].unit_cost,
+                    count as u16,
+                ));
+            }
+
+            invoices.push(gen_invoice(
+                in_num.unsigned_abs(),
+                total_price,
+                c.contract_id.unwrap(),
+                &lifespan,
+            ));
+        }
     }
-    println!("INSERTING contracts");
-    insert_with_copy(&cfg, &contracts);
 
-    let password_faker = StringFaker::with(String::from("0123456789abcdef").into_bytes(), 64..65);
+    simulation::dunning::terminate_for_non_payment(
+        &mut contracts,
+        &mut invoices,
+        &mut iih,
+        &scenario,
+    );
+
+    println!("Generating plans and subscriptions");
+    create_table::<Plan>(&cfg);
+    create_table::<PlanRate>(&cfg);
+    create_table::<Subscription>(&cfg);
+    let (plans, plan_rates) = gen_plans(get_last_id(&cfg, "plan", "plan_id"), iid + 8);
+    for plan in plans.iter() {
+        i_items.push(InvoiceItem::new(
+            Some(plan.invoice_item_id),
+            format!("Plan {}", plan.name),
+            plan.monthly_fee.to_f32().unwrap_or_default(),
+        ));
+    }
+    let mut subscriptions: Vec<Subscription> = Vec::with_capacity(contracts_total);
+    let last_subscription_id = get_last_id(&cfg, "subscription", "subscription_id");
+    for c in contracts.iter() {
+        subscriptions.extend(gen_subscriptions(
+            last_subscription_id + subscriptions.len() as u32,
+            c,
+            &plans,
+            scenario.plan_change_percent,
+        ));
+    }
+    simulation::billing::add_recurring_fees(
+        &mut invoices,
+        &mut iih,
+        &subscriptions,
+        &plans,
+        in_num.unsigned_abs(),
+    );
+
+    println!("Applying bonus credits");
+    create_table::<CreditNote>(&cfg);
+    let credit_notes = simulation::billing::apply_bonus_credit(
+        &contracts,
+        &mut invoices,
+        &mut iih,
+        iid + 7,
+        u64::from(get_last_id(&cfg, "credit_note", "credit_note_number")).max(98_000_000),
+    );
+
+    let mut credentials = Credentials::new(&scenario);
+    let mut addresses: Vec<Address> = Vec::<Address>::with_capacity(contracts_total);
     {
         let mut participants: Vec<Participant> =
             Vec::<Participant>::with_capacity(contracts_total * 2);
@@ -194,143 +423,317 @@ fn main() -> () {
         let mut number_requests: Vec<NumberRequest> =
             Vec::<NumberRequest>::with_capacity(contracts_total);
         {
-            let mut addresses: Vec<Address> = Vec::<Address>::with_capacity(contracts_total);
             for c in contracts.iter() {
-                let participaints_count = (1..=4).fake::<u8>();
+                let lifespan = c.lifespan();
+                let dictionary = &dictionaries[&c.locale];
+                let segment = segments[&c.segment[..]];
+                let participaints_count = gen_count(&segment.participants);
                 let mut idx = 0;
                 aid += 1;
                 while idx < participaints_count {
                     pid += 1;
-                    participants.push(gen_participant(
+                    let (access_level, participant_lifespan) = gen_access_level(
+                        idx,
+                        &lifespan,
+                        segment,
+                        scenario.participant_churn_percent,
+                    );
+                    let mut participant = gen_participant(
                         pid,
                         c.contract_id.unwrap(),
-                        &password_faker,
-                    ));
+                        access_level,
+                        &participant_lifespan,
+                        dictionary,
+                        &mut identities,
+                        &mut credentials,
+                    );
+                    participant.channel_limit = gen_channel_limit(&segment.participant_channels);
+                    participants.push(participant);
                     idx += 1;
                 }
-                addresses.push(gen_address(aid, c.contract_id.unwrap()));
+                addresses.push(gen_address(aid, c.contract_id.unwrap(), dictionary));
             }
             println!("INSERTING addresses");
             insert_with_copy(&cfg, &addresses);
         }
         println!("INSERTING participants");
         insert_with_copy(&cfg, &participants);
+        if scenario.generate_permissions {
+            println!("INSERTING participant permissions");
+            create_table::<Permission>(&cfg);
+            insert_with_copy(&cfg, &gen_permissions(&participants));
+        }
+
+        let mut number_plan = load_number_plan(&cfg, &scenario);
+        // Numbers are allocated in the country of the contract's locale.
+        let contract_countries: HashMap<u32, u16> = contracts
+            .iter()
+            .map(|c| {
+                (
+                    c.contract_id.unwrap(),
+                    dictionaries[&c.locale].phone_country_code,
+                )
+            })
+            .collect();
+        let mut did_blocks: HashMap<u32, usize> = HashMap::new();
+        if scenario.company_did_block_size > 0 {
+            for c in contracts
+                .iter()
+                .filter(|c| segments[&c.segment[..]].company)
+            {
+                let cid = c.contract_id.unwrap();
+                did_blocks.insert(
+                    cid,
+                    number_plan.allocate_block(
+                        contract_countries[&cid],
+                        scenario.company_did_block_size,
+                        cid,
+                    ),
+                );
+            }
+        }
 
+        let contract_segments: HashMap<u32, &String> = contracts
+            .iter()
+            .map(|c| (c.contract_id.unwrap(), &c.segment))
+            .collect();
         let mut voip_numbers: Vec<VoipNumber> =
             Vec::<VoipNumber>::with_capacity(participants.len() * 2);
-        let password_faker =
-            StringFaker::with(String::from("0123456789abcdef").into_bytes(), 32..33);
-
         println!(
-            "GENERATING voip_numbers, maximum of {}",
-            participants.len() * 4
+            "GENERATING voip_numbers for {} participants",
+            participants.len()
         );
         for p in participants.iter() {
-            let numbers_count = (1..=4).fake::<u8>();
+            let lifespan = p.lifespan();
+            let numbers_count = gen_count(
+                &segments[&contract_segments[&p.contract_id][..]].numbers_per_participant,
+            );
             for _ in 0..numbers_count {
                 vid += 1;
-                voip_numbers.push(gen_voip_number(vid, p.participant_id, &password_faker));
+                let number = match did_blocks.get(&p.contract_id) {
+                    Some(block) => number_plan.allocate_from_block(*block),
+                    None => number_plan.allocate(contract_countries[&p.contract_id]),
+                };
+                voip_numbers.push(gen_voip_number(
+                    vid,
+                    p.participant_id,
+                    Some(&gen_lifespan(&lifespan, scenario.number_churn_percent)),
+                    number,
+                ));
             }
             let has_number_request = Boolean(10).fake();
             if has_number_request {
                 vid += 1;
-                let vn = gen_voip_number(vid, None, &password_faker);
-                number_requests.push(gen_number_request(
-                    vn.number_id.unwrap(),
-                    p.participant_id.unwrap(),
-                ));
+                let request =
+                    gen_number_request(vid, p.participant_id.unwrap(), &lifespan, &scenario);
+                // Approved numbers are assigned when activated, numbers of
+                // turned down requests go back to the free pool.
+                let number_lifespan = request
+                    .activation()
+                    .map(|at| gen_lifespan_from(at, &lifespan, scenario.number_churn_percent));
+                let mut vn = gen_voip_number(
+                    vid,
+                    number_lifespan.and(p.participant_id),
+                    number_lifespan.as_ref(),
+                    number_plan.allocate(contract_countries[&p.contract_id]),
+                );
+                if request.status == REQUEST_REJECTED || request.status == REQUEST_EXPIRED {
+                    vn.current_state = NUMBER_STATE_FREE;
+                }
+                number_requests.push(request);
                 voip_numbers.push(vn);
             }
         }
 
-        println!("INSERTING voip_numbers");
-        insert_with_copy(&cfg, &voip_numbers);
+        println!("SIMULATING number lifecycle");
+        simulation::numbers::simulate_number_lifecycle(&mut voip_numbers, &number_plan, &scenario);
+        for number in voip_numbers.iter_mut() {
+            number.set_sip_password(&mut credentials);
+        }
+        if let Some(export_dir) = export_dir {
+            println!("Exporting number plan");
+            export::number_plan::write_number_plan(Path::new(export_dir), &number_plan)
+                .expect("Failed to export number plan");
+        }
+
         println!("INSERTING number_requests");
+        alter_table::<NumberRequest>(&cfg);
         insert_with_copy(&cfg, &number_requests);
 
-        let price_lists = vec![
-            gen_price_list(prid + 1, 49, 30, 60, 20),
-            gen_price_list(prid + 2, 420, 10, 1, 1),
-            gen_price_list(prid + 3, 421, 15, 60, 1),
-            gen_price_list(prid + 4, 48, 20, 60, 10),
-            gen_price_list(prid + 5, 43, 35, 60, 20),
-        ];
+        let price_lists = gen_price_lists(prid);
 
         println!("INSERTING price_list");
+        alter_table::<PriceList>(&cfg);
         insert_with_copy(&cfg, &price_lists);
         println!("GENERATING cdrs");
 
+        let active_numbers: Vec<(&VoipNumber, Lifespan)> = voip_numbers
+            .iter()
+            .filter_map(|n| Some((n, n.lifespan()?)))
+            .collect();
+        let owners: HashMap<u32, u32> = participants
+            .iter()
+            .map(|p| (p.participant_id.unwrap(), p.contract_id))
+            .collect();
+        let number_segments: Vec<&Segment> = active_numbers
+            .iter()
+            .map(|(n, _)| {
+                let cid = owners[&n.participant_id.unwrap()];
+                segments[&contract_segments[&cid][..]]
+            })
+            .collect();
+        let weights: Vec<f64> = number_segments.iter().map(|s| s.call_weight).collect();
+        let countries = price_list_countries();
+        let traffic = TrafficModel::new(&scenario, &weights);
+        // Country of the remote party or network, foreign by the segment's share.
+        let pick_country = |idx: usize| -> u16 {
+            let domestic = active_numbers[idx].0.number.country_code;
+            if !Boolean(number_segments[idx].international_percent).fake::<bool>() {
+                return domestic;
+            }
+            let foreign: Vec<u16> = countries
+                .iter()
+                .copied()
+                .filter(|c| *c != domestic)
+                .collect();
+            foreign[(0..foreign.len()).fake::<usize>()]
+        };
         let mut calls: Vec<CallDetailRecord> = Vec::<CallDetailRecord>::with_capacity(calls_count);
         for n in 1..=calls_count {
-            let rnd = (0..5).fake::<usize>();
-            let rnd_num = (0..voip_numbers.len()).fake::<usize>();
+            let idx = traffic.pick_number();
+            let (number, lifespan) = &active_numbers[idx];
+            let pcc = pick_country(idx);
             calls.push(gen_cdr(
                 cdrid + (n as u32),
-                price_lists[rnd].phone_country_code,
-                price_lists[rnd].price_list_id.unwrap(),
-                voip_numbers[rnd_num].number.to_string(),
-                voip_numbers[rnd_num].number_id.unwrap(),
+                pcc,
+                number.number,
+                number.number_id.unwrap(),
+                traffic.call_date(lifespan),
+                traffic.outcome(),
             ));
         }
-        println!("INSERTING cdrs");
-        insert_with_copy(&cfg, &calls);
-    }
 
-    let mut i_items: Vec<InvoiceItem> = Vec::<InvoiceItem>::with_capacity(10);
-    i_items.push(gen_invoice_item(iid + 1, String::from("Calls")));
-    i_items.push(gen_invoice_item(iid + 2, String::from("Phone 3CX")));
-    i_items.push(gen_invoice_item(iid + 3, String::from("Phone 4G")));
-    i_items.push(gen_invoice_item(iid + 4, String::from("Phone 10L")));
-    i_items.push(gen_invoice_item(iid + 5, String::from("Phone cable")));
-    i_items.push(gen_invoice_item(iid + 6, String::from("Phone 787FU")));
-
-    let mut invoices: Vec<Invoice> = Vec::<Invoice>::with_capacity(contracts_total * 3);
-    let mut iih: Vec<InvoiceHasItems> = Vec::<InvoiceHasItems>::with_capacity(contracts_total * 5);
-
-    println!("Generating invoices");
-    for c in contracts.iter() {
-        let invoices_count = (0..8).fake::<u8>();
-        for _ in 1..invoices_count {
-            in_num += 1;
-            let items_count = (2..4).fake::<u8>();
-            let mut total_price = 0f32;
-            let mut picked_items: Vec<usize> = vec![];
-            for _i in 1..items_count {
-                let mut rnd_item: usize;
-                loop {
-                    rnd_item = (0..6).fake::<usize>();
-
-                    if !picked_items.contains(&rnd_item) {
-                        break;
-                    }
-                }
-
-                picked_items.push(rnd_item);
-
-                total_price += i_items[rnd_item].unit_cost;
-
-                iih.push(InvoiceHasItems::new(
-                    in_num.unsigned_abs(),
-                    i_items[rnd_item].item_id.unwrap(),
-                    i_items[rnd_item].unit_cost,
-                    1,
-                ));
-            }
-
-            invoices.push(gen_invoice(
-                in_num.unsigned_abs(),
-                total_price,
-                c.contract_id.unwrap(),
+        println!("GENERATING sms and data sessions");
+        create_table::<SmsRecord>(&cfg);
+        create_table::<DataSession>(&cfg);
+        let sms_id = get_last_id(&cfg, "sms_record", "sms_id");
+        let sms_count = (calls_count as f64 * scenario.sms_per_call) as u32;
+        let mut messages: Vec<SmsRecord> = Vec::with_capacity(sms_count as usize);
+        for n in 1..=sms_count {
+            let idx = traffic.pick_number();
+            let (number, lifespan) = &active_numbers[idx];
+            messages.push(gen_sms(
+                sms_id + n,
+                pick_country(idx),
+                number.number,
+                number.number_id.unwrap(),
+                traffic.call_date(lifespan),
             ));
         }
+        let session_id = get_last_id(&cfg, "data_session", "session_id");
+        let session_count = (calls_count as f64 * scenario.data_sessions_per_call) as u32;
+        let mut sessions: Vec<DataSession> = Vec::with_capacity(session_count as usize);
+        for n in 1..=session_count {
+            let idx = traffic.pick_number();
+            let (number, lifespan) = &active_numbers[idx];
+            sessions.push(gen_data_session(
+                session_id + n,
+                pick_country(idx),
+                number.number_id.unwrap(),
+                traffic.call_date(lifespan),
+            ));
+        }
+        drop(active_numbers);
+        simulation::rating::rate_sms(&mut messages, &price_lists);
+        simulation::rating::rate_data_sessions(&mut sessions, &price_lists);
+        simulation::policy::enforce_foreign_block(
+            &mut calls,
+            &voip_numbers,
+            scenario.foreign_block_attempt_percent,
+        );
+        simulation::legs::link_call_legs(&mut calls, &voip_numbers, &scenario);
+        simulation::channels::enforce_channel_limits(
+            &mut calls,
+            &voip_numbers,
+            &participants,
+            &contracts,
+        );
+        simulation::rating::rate_calls(&mut calls, &price_lists);
+        let mut tariff =
+            simulation::plans::Tariff::new(&price_lists, &plans, &plan_rates, &subscriptions);
+
+        println!("SIMULATING spending limits");
+        create_table::<Notification>(&cfg);
+        let notifications = simulation::limits::simulate_limits(
+            &mut calls,
+            &voip_numbers,
+            &participants,
+            &contracts,
+            &mut tariff,
+            scenario.block_over_balance_limit,
+            get_last_id(&cfg, "notification", "notification_id"),
+        );
+
+        println!("INSERTING cdrs");
+        alter_table::<CallDetailRecord>(&cfg);
+        insert_with_copy(&cfg, &calls);
+        println!("INSERTING notifications");
+        insert_with_copy(&cfg, &notifications);
+        println!("INSERTING sms records");
+        insert_with_copy(&cfg, &messages);
+        println!("INSERTING data sessions");
+        insert_with_copy(&cfg, &sessions);
+
+        println!("SIMULATING dunning");
+        create_table::<PaymentReminder>(&cfg);
+        let reminders = simulation::dunning::simulate_dunning(
+            &invoices,
+            &participants,
+            &mut voip_numbers,
+            &scenario,
+            get_last_id(&cfg, "payment_reminder", "reminder_id"),
+        );
+        println!("INSERTING voip_numbers");
+        alter_table::<VoipNumber>(&cfg);
+        insert_with_copy(&cfg, &voip_numbers);
+        println!("INSERTING payment reminders");
+        insert_with_copy(&cfg, &reminders);
     }
+    println!("INSERTING contracts");
+    alter_table::<Contract>(&cfg);
+    insert_with_copy(&cfg, &contracts);
 
     println!("inserting invoice items");
     insert_with_copy(&cfg, &i_items);
+    println!("Inserting plans and subscriptions");
+    insert_with_copy(&cfg, &plans);
+    insert_with_copy(&cfg, &plan_rates);
+    insert_with_copy(&cfg, &subscriptions);
     println!("Inserting invoices");
     insert_with_copy(&cfg, &invoices);
     println!("Inserting invoice has items");
     insert_with_copy(&cfg, &iih);
+    println!("Inserting credit notes");
+    insert_with_copy(&cfg, &credit_notes);
+
+    if let Some(export_dir) = export_dir {
+        println!("Exporting ISDOC invoices");
+        export::isdoc::write_isdoc(
+            &Path::new(export_dir).join("isdoc"),
+            &invoices,
+            &iih,
+            &i_items,
+            &contracts,
+            &addresses,
+        )
+        .expect("Failed to export ISDOC invoices");
+        println!("Exporting QR payments");
+        export::spayd::write_spayd(Path::new(export_dir), &invoices, &iih, &contracts)
+            .expect("Failed to export QR payments");
+        println!("Exporting test credentials");
+        export::credentials::write_credentials(Path::new(export_dir), &credentials.recorded)
+            .expect("Failed to export test credentials");
+    }
 
     enable_primary_keys(&cfg);
     enable_foreign_keys::<Contract>(&cfg);
@@ -343,4 +746,15 @@ fn main() -> () {
     enable_foreign_keys::<Invoice>(&cfg);
     enable_foreign_keys::<InvoiceItem>(&cfg);
     enable_foreign_keys::<InvoiceHasItems>(&cfg);
+    enable_foreign_keys::<Notification>(&cfg);
+    enable_foreign_keys::<CreditNote>(&cfg);
+    enable_foreign_keys::<PaymentReminder>(&cfg);
+    enable_foreign_keys::<Plan>(&cfg);
+    enable_foreign_keys::<PlanRate>(&cfg);
+    enable_foreign_keys::<Subscription>(&cfg);
+    enable_foreign_keys::<SmsRecord>(&cfg);
+    enable_foreign_keys::<DataSession>(&cfg);
+    if scenario.generate_permissions {
+        enable_foreign_keys::<Permission>(&cfg);
+    }
 }
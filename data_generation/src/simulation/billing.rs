@@ -0,0 +1,255 @@
+use super::*;
+use crate::entities::generators::gen_invoice_at;
+use crate::entities::*;
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::{BTreeMap, HashMap};
+
+pub const BONUS_CREDIT_REASON: &str = "BONUS_CREDIT";
+
+/// Start of the calendar month `date` falls into and of the month after.
+fn month_bounds(date: &DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let first = |year: i32, month: u32| {
+        Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(year, month, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        )
+    };
+    let (year, month) = (date.year(), date.month());
+    let next = if month == 12 {
+        first(year + 1, 1)
+    } else {
+        first(year, month + 1)
+    };
+    (first(year, month), next)
+}
+
+/// Bills the monthly fee of every plan a contract was subscribed to in each
+/// calendar month up to now. The fees go on the contract's first invoice of
+/// the month, months without one get a billing invoice of their own numbered
+/// after `last_invoice_number`. Fees are prorated by the part of the month the
+/// subscription covered, so plan changes and contracts starting or ending mid
+/// month pay for the days they had.
+pub fn add_recurring_fees(
+    invoices: &mut Vec<Invoice>,
+    invoice_lines: &mut Vec<InvoiceHasItems>,
+    subscriptions: &[Subscription],
+    plans: &[Plan],
+    last_invoice_number: u64,
+) {
+    let plans: HashMap<u32, &Plan> = plans.iter().map(|p| (p.plan_id, p)).collect();
+    let mut by_contract: BTreeMap<u32, Vec<&Subscription>> = BTreeMap::new();
+    for subscription in subscriptions {
+        by_contract
+            .entry(subscription.contract_id)
+            .or_default()
+            .push(subscription);
+    }
+
+    let mut order: Vec<(DateTime<FixedOffset>, usize)> = invoices
+        .iter()
+        .enumerate()
+        .map(|(idx, i)| (parse_date(&i.created_at), idx))
+        .collect();
+    order.sort();
+    let mut first_invoices: HashMap<(u32, String), usize> = HashMap::new();
+    for (date, idx) in order {
+        first_invoices
+            .entry((invoices[idx].contract_id, billing_period(&date)))
+            .or_insert(idx);
+    }
+
+    let now = Utc::now();
+    let mut invoice_number = last_invoice_number;
+    for (contract_id, subscriptions) in by_contract {
+        let lifespans: Vec<Lifespan> = subscriptions.iter().map(|s| s.lifespan()).collect();
+        let first = match lifespans.iter().map(|l| l.start).min() {
+            Some(first) => first,
+            None => continue,
+        };
+        let last = lifespans.iter().map(|l| l.until()).max().unwrap().min(now);
+        let mut month_start = month_bounds(&first).0;
+        while month_start < last {
+            let (start, end) = month_bounds(&month_start);
+            month_start = end;
+            let month = (end - start).num_seconds();
+            let mut fees: BTreeMap<u32, Decimal> = BTreeMap::new();
+            for (subscription, lifespan) in subscriptions.iter().zip(lifespans.iter()) {
+                let covered = (lifespan.until().min(end) - lifespan.start.max(start)).num_seconds();
+                if covered <= 0 {
+                    continue;
+                }
+                let plan = plans[&subscription.plan_id];
+                *fees.entry(plan.plan_id).or_insert(Decimal::ZERO) +=
+                    plan.monthly_fee * Decimal::from(covered) / Decimal::from(month);
+            }
+            fees.retain(|_, fee| fee.round_dp(2) > Decimal::ZERO);
+            if fees.is_empty() {
+                continue;
+            }
+
+            let issued = start.max(first);
+            let period = billing_period(&issued.into());
+            let idx = *first_invoices
+                .entry((contract_id, period))
+                .or_insert_with(|| {
+                    invoice_number += 1;
+                    invoices.push(gen_invoice_at(invoice_number, 0.0, contract_id, issued));
+                    invoices.len() - 1
+                });
+            let invoice = &mut invoices[idx];
+            for (plan_id, fee) in fees {
+                let fee_f32 = fee.round_dp(2).to_f32().unwrap_or_default();
+                invoice.amount += fee_f32;
+                invoice_lines.push(InvoiceHasItems::new(
+                    invoice.invoice_number,
+                    plans[&plan_id].invoice_item_id,
+                    fee_f32,
+                    1,
+                ));
+            }
+        }
+    }
+}
+
+/// Consumes each contract's `bonus_amount` on its earliest invoices until the
+/// bonus is exhausted. Unpaid invoices get a negative credit line and a lower
+/// amount, invoices that were already paid are corrected by a credit note.
+pub fn apply_bonus_credit(
+    contracts: &[Contract],
+    invoices: &mut [Invoice],
+    invoice_lines: &mut Vec<InvoiceHasItems>,
+    credit_item_id: u32,
+    last_credit_note_number: u64,
+) -> Vec<CreditNote> {
+    let mut by_contract: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (idx, invoice) in invoices.iter().enumerate() {
+        by_contract
+            .entry(invoice.contract_id)
+            .or_default()
+            .push(idx);
+    }
+
+    let mut credit_notes: Vec<CreditNote> = vec![];
+    let mut credit_note_number = last_credit_note_number;
+    for contract in contracts {
+        let mut remaining = match contract.bonus_amount {
+            Some(bonus) => bonus,
+            None => continue,
+        };
+        let mut indexes = match contract
+            .contract_id
+            .and_then(|cid| by_contract.remove(&cid))
+        {
+            Some(indexes) => indexes,
+            None => continue,
+        };
+        indexes.sort_by_key(|idx| parse_date(&invoices[*idx].created_at));
+
+        for idx in indexes {
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+            let invoice = &mut invoices[idx];
+            let amount = Decimal::from_f32(invoice.amount).unwrap_or_default();
+            let credit = remaining
+                .min(amount)
+                .round_dp_with_strategy(2, RoundingStrategy::ToZero);
+            if credit <= Decimal::ZERO {
+                continue;
+            }
+            remaining -= credit;
+            let credit_f32 = credit.to_f32().unwrap_or_default();
+
+            match &invoice.paid {
+                Some(paid) => {
+                    credit_note_number += 1;
+                    credit_notes.push(CreditNote {
+                        credit_note_number,
+                        invoice_number: invoice.invoice_number,
+                        contract_id: invoice.contract_id,
+                        amount: credit_f32,
+                        reason: BONUS_CREDIT_REASON.to_string(),
+                        created_at: paid.clone(),
+                    });
+                }
+                None => {
+                    invoice.amount = (amount - credit).to_f32().unwrap_or_default();
+                    invoice_lines.push(InvoiceHasItems::new(
+                        invoice.invoice_number,
+                        credit_item_id,
+                        -credit_f32,
+                        1,
+                    ));
+                }
+            }
+        }
+    }
+    credit_notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{contract, date};
+    use super::*;
+
+    fn invoice(number: u64, day: &str, amount: f32, paid: bool) -> Invoice {
+        Invoice::new(
+            number,
+            amount,
+            21,
+            date(day),
+            date(day),
+            date(day),
+            if paid { Some(date(day)) } else { None },
+            1,
+        )
+    }
+
+    #[test]
+    fn bonus_is_used_up_on_the_earliest_invoices() {
+        let mut contract = contract(1);
+        contract.bonus_amount = Some(Decimal::from(250));
+        let mut invoices = vec![
+            invoice(4, "2024-03-01", 100.0, false),
+            invoice(2, "2024-01-01", 100.0, false),
+            invoice(5, "2024-04-01", 100.0, false),
+            invoice(3, "2024-02-01", 100.0, false),
+            invoice(1, "2023-12-01", 40.0, true),
+        ];
+        let mut lines: Vec<InvoiceHasItems> = invoices
+            .iter()
+            .map(|i| InvoiceHasItems::new(i.invoice_number, 1, i.amount, 1))
+            .collect();
+
+        let credit_notes = apply_bonus_credit(&[contract], &mut invoices, &mut lines, 99, 0);
+
+        assert_eq!(credit_notes.len(), 1);
+        assert_eq!(credit_notes[0].invoice_number, 1);
+        assert_eq!(credit_notes[0].amount, 40.0);
+        let credits: Vec<(u64, f32)> = lines
+            .iter()
+            .filter(|l| l.invoice_item_id == 99)
+            .map(|l| (l.invoice_number, l.item_unit_cost))
+            .collect();
+        assert_eq!(credits, [(2, -100.0), (3, -100.0), (4, -10.0)]);
+        let credited: f32 = credit_notes.iter().map(|n| n.amount).sum::<f32>()
+            - credits.iter().map(|(_, c)| c).sum::<f32>();
+        assert_eq!(credited, 250.0);
+
+        for invoice in &invoices {
+            let total: f32 = lines
+                .iter()
+                .filter(|l| l.invoice_number == invoice.invoice_number)
+                .map(|l| l.item_unit_cost * f32::from(l.item_count))
+                .sum();
+            assert!(invoice.amount >= 0.0, "{:?}", invoice);
+            assert!(total >= 0.0, "{:?}", invoice);
+        }
+        let untouched = invoices.iter().find(|i| i.invoice_number == 5).unwrap();
+        assert_eq!(untouched.amount, 100.0);
+    }
+}
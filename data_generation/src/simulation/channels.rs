@@ -0,0 +1,143 @@
+use super::*;
+use crate::entities::*;
+use chrono::Duration;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+pub const CHANNEL_LIMIT: &str = "CHANNEL_LIMIT";
+
+/// Calls in progress of one participant or contract, by their end.
+#[derive(Default)]
+struct Channels {
+    busy: BinaryHeap<Reverse<DateTime<FixedOffset>>>,
+}
+
+impl Channels {
+    /// Whether another call can start at `at`, no `limit` means unlimited.
+    fn is_free(&mut self, at: DateTime<FixedOffset>, limit: Option<u32>) -> bool {
+        while self.busy.peek().is_some_and(|Reverse(end)| *end <= at) {
+            self.busy.pop();
+        }
+        limit.is_none_or(|limit| self.busy.len() < limit as usize)
+    }
+}
+
+/// Replays calls chronologically and rejects those that would exceed the
+/// `channel_limit` of the participant or its contract as CONGESTION. Later
+/// legs of a rejected call are dropped as they never happened, and a leg
+/// whose transfer got rejected talks on until the end of the call.
+pub fn enforce_channel_limits(
+    calls: &mut Vec<CallDetailRecord>,
+    voip_numbers: &[VoipNumber],
+    participants: &[Participant],
+    contracts: &[Contract],
+) {
+    let participants: HashMap<u32, &Participant> = participants
+        .iter()
+        .filter_map(|p| Some((p.participant_id?, p)))
+        .collect();
+    let contract_limits: HashMap<u32, Option<u32>> = contracts
+        .iter()
+        .filter_map(|c| Some((c.contract_id?, c.channel_limit)))
+        .collect();
+    let owners: HashMap<u32, &Participant> = voip_numbers
+        .iter()
+        .filter_map(|n| Some((n.number_id?, *participants.get(&n.participant_id?)?)))
+        .collect();
+
+    let mut order: Vec<(DateTime<FixedOffset>, usize)> = calls
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.length > 0)
+        .map(|(idx, c)| (parse_date(&c.call_date), idx))
+        .collect();
+    order.sort();
+    let legs: HashMap<(u32, u8), usize> = calls
+        .iter()
+        .enumerate()
+        .map(|(idx, c)| ((c.linked_id, c.leg), idx))
+        .collect();
+
+    let mut participant_channels: HashMap<u32, Channels> = HashMap::new();
+    let mut contract_channels: HashMap<u32, Channels> = HashMap::new();
+    let mut rejected: HashSet<u32> = HashSet::new();
+    for (start, idx) in order {
+        let call = &calls[idx];
+        let owner = match owners.get(&call.number_id) {
+            Some(owner) => *owner,
+            None => continue,
+        };
+        let (pid, cid) = (owner.participant_id.unwrap(), owner.contract_id);
+        if rejected.contains(&call.linked_id) {
+            continue;
+        }
+        let participant = participant_channels.entry(pid).or_default();
+        let contract = contract_channels.entry(cid).or_default();
+        if !participant.is_free(start, owner.channel_limit)
+            || !contract.is_free(start, contract_limits.get(&cid).copied().flatten())
+        {
+            let linked_id = call.linked_id;
+            if call.leg_type.as_deref() == Some(LEG_TRANSFER) {
+                let end = start + Duration::seconds(i64::from(call.length));
+                let prev_idx = legs[&(linked_id, call.leg - 1)];
+                let prev = &mut calls[prev_idx];
+                prev.length = (end - parse_date(&prev.call_date)).num_seconds() as u16;
+                prev.billsec = prev.length - prev.ring_duration;
+                participant.busy.push(Reverse(end));
+                contract.busy.push(Reverse(end));
+            }
+            let call = &mut calls[idx];
+            call.disposition = DISPOSITION_CONGESTION.to_string();
+            call.length = 0;
+            call.ring_duration = 0;
+            call.billsec = 0;
+            call.failure_reason = Some(CHANNEL_LIMIT.to_string());
+            rejected.insert(linked_id);
+            continue;
+        }
+        let end = start + Duration::seconds(i64::from(call.length));
+        participant.busy.push(Reverse(end));
+        contract.busy.push(Reverse(end));
+    }
+    calls.retain(|c| c.leg == 1 || c.length == 0 || !rejected.contains(&c.linked_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::*;
+    use super::*;
+
+    #[test]
+    fn rejected_transfer_keeps_the_first_leg_talking() {
+        let mut owner = participant(1, 1);
+        owner.channel_limit = Some(1);
+        let voip_numbers = vec![
+            voip_number(1, Some(1), 601_000_001),
+            voip_number(2, Some(1), 601_000_002),
+        ];
+        // The first leg got cut to 30 s by a transfer 35 s after it started,
+        // exactly when another call takes the only channel.
+        let mut first = call(1, 1, "2024-01-05T10:00:00+00:00", 30);
+        let blocker = call(2, 2, "2024-01-05T10:00:35+00:00", 60);
+        let mut transfer = call(3, 1, "2024-01-05T10:00:35+00:00", 565);
+        transfer.incoming_outgoing = false;
+        transfer.linked_id = 1;
+        transfer.leg = 2;
+        transfer.leg_type = Some(LEG_TRANSFER.to_string());
+        first.incoming_outgoing = true;
+        let mut calls = vec![first, blocker, transfer];
+
+        enforce_channel_limits(&mut calls, &voip_numbers, &[owner], &[contract(1)]);
+
+        assert_eq!(calls.len(), 3);
+        let (first, blocker, transfer) = (&calls[0], &calls[1], &calls[2]);
+        assert_eq!(blocker.disposition, DISPOSITION_ANSWERED);
+        assert_eq!(transfer.disposition, DISPOSITION_CONGESTION);
+        assert_eq!(transfer.failure_reason.as_deref(), Some(CHANNEL_LIMIT));
+        assert_eq!(transfer.length, 0);
+        assert_eq!(first.disposition, DISPOSITION_ANSWERED);
+        assert_eq!(first.length, 605);
+        assert_eq!(first.ring_duration, 5);
+        assert_eq!(first.billsec, 600);
+    }
+}
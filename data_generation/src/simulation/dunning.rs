@@ -0,0 +1,224 @@
+use super::*;
+use crate::config::Scenario;
+use crate::entities::*;
+use chrono::{Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// Terminates contracts with an invoice left unpaid for longer than the
+/// termination threshold. Invoices the contract would have received after its
+/// termination are dropped together with their lines.
+pub fn terminate_for_non_payment(
+    contracts: &mut [Contract],
+    invoices: &mut Vec<Invoice>,
+    invoice_lines: &mut Vec<InvoiceHasItems>,
+    scenario: &Scenario,
+) {
+    let days = match scenario.dunning_terminate_after_days {
+        Some(days) => Duration::days(i64::from(days)),
+        None => return,
+    };
+    let now = Utc::now();
+    let mut terminated: HashMap<u32, DateTime<Utc>> = HashMap::new();
+    for invoice in invoices.iter().filter(|i| i.paid.is_none()) {
+        let at = parse_date(&invoice.maturity).with_timezone(&Utc) + days;
+        if at >= now {
+            continue;
+        }
+        let earliest = terminated.entry(invoice.contract_id).or_insert(at);
+        if at < *earliest {
+            *earliest = at;
+        }
+    }
+
+    for contract in contracts.iter_mut() {
+        let at = match contract.contract_id.and_then(|cid| terminated.get(&cid)) {
+            Some(at) => *at,
+            None => continue,
+        };
+        if contract.lifespan().end.is_none_or(|end| at < end) {
+            contract.deleted_at = Some(at.to_rfc3339());
+        } else {
+            terminated.remove(&contract.contract_id.unwrap());
+        }
+    }
+
+    let mut dropped: HashSet<u64> = HashSet::new();
+    invoices.retain(|i| match terminated.get(&i.contract_id) {
+        Some(at) if parse_date(&i.created_at) > *at => {
+            dropped.insert(i.invoice_number);
+            false
+        }
+        _ => true,
+    });
+    invoice_lines.retain(|l| !dropped.contains(&l.invoice_number));
+}
+
+/// Sends a payment reminder for every configured day offset after maturity that
+/// passed before the invoice got paid. Contracts with an invoice unpaid for
+/// longer than the suspension threshold get all their numbers suspended.
+pub fn simulate_dunning(
+    invoices: &[Invoice],
+    participants: &[Participant],
+    voip_numbers: &mut [VoipNumber],
+    scenario: &Scenario,
+    last_reminder_id: u32,
+) -> Vec<PaymentReminder> {
+    let now = Utc::now();
+    let mut reminders: Vec<PaymentReminder> = vec![];
+    let mut reminder_id = last_reminder_id;
+    let mut suspended: HashSet<u32> = HashSet::new();
+
+    for invoice in invoices {
+        let maturity = parse_date(&invoice.maturity);
+        let settled = match &invoice.paid {
+            Some(paid) => parse_date(paid).with_timezone(&Utc),
+            None => now,
+        };
+        let overdue = settled - maturity.with_timezone(&Utc);
+        if overdue <= Duration::zero() {
+            continue;
+        }
+
+        for (level, days) in scenario.dunning_reminder_days.iter().enumerate() {
+            let days = Duration::days(i64::from(*days));
+            if days >= overdue {
+                continue;
+            }
+            reminder_id += 1;
+            reminders.push(PaymentReminder {
+                reminder_id: Some(reminder_id),
+                invoice_number: invoice.invoice_number,
+                contract_id: invoice.contract_id,
+                reminder_level: (level + 1) as u8,
+                amount_due: invoice.amount * (100 + u32::from(invoice.tax_value_percent)) as f32
+                    / 100f32,
+                sent_at: (maturity + days).to_rfc3339(),
+            });
+        }
+
+        if invoice.paid.is_some() {
+            continue;
+        }
+        if overdue > Duration::days(i64::from(scenario.dunning_suspend_after_days)) {
+            suspended.insert(invoice.contract_id);
+        }
+    }
+
+    let owners: HashMap<u32, u32> = participants
+        .iter()
+        .filter_map(|p| Some((p.participant_id?, p.contract_id)))
+        .collect();
+    for number in voip_numbers.iter_mut() {
+        let contract_id = number.participant_id.and_then(|pid| owners.get(&pid));
+        if number.current_state == NUMBER_STATE_ACTIVE
+            && contract_id.is_some_and(|cid| suspended.contains(cid))
+        {
+            number.current_state = NUMBER_STATE_SUSPENDED;
+        }
+    }
+    reminders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::*;
+    use super::*;
+
+    fn invoice(number: u64, contract_id: u32, maturity: &str, paid: Option<&str>) -> Invoice {
+        Invoice::new(
+            number,
+            100.0,
+            21,
+            maturity.to_string(),
+            maturity.to_string(),
+            maturity.to_string(),
+            paid.map(str::to_string),
+            contract_id,
+        )
+    }
+
+    #[test]
+    fn reminders_follow_maturity_and_overdue_contracts_get_suspended() {
+        let scenario = Scenario {
+            dunning_reminder_days: vec![7, 14, 30],
+            dunning_suspend_after_days: 30,
+            ..Scenario::default()
+        };
+        let invoices = [
+            invoice(1, 1, &date("2024-01-01"), Some(&date("2024-01-20"))),
+            invoice(2, 2, &date("2024-01-01"), None),
+            invoice(3, 3, &date("2024-01-01"), Some(&date("2023-12-20"))),
+        ];
+        let participants = [participant(1, 1), participant(2, 2), participant(3, 3)];
+        let mut voip_numbers = [
+            voip_number(1, Some(1), 601_000_001),
+            voip_number(2, Some(2), 601_000_002),
+            voip_number(3, Some(3), 601_000_003),
+        ];
+
+        let reminders = simulate_dunning(&invoices, &participants, &mut voip_numbers, &scenario, 0);
+
+        let sent: Vec<(u64, u8, String)> = reminders
+            .iter()
+            .map(|r| (r.invoice_number, r.reminder_level, r.sent_at.clone()))
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                (1, 1, date("2024-01-08")),
+                (1, 2, date("2024-01-15")),
+                (2, 1, date("2024-01-08")),
+                (2, 2, date("2024-01-15")),
+                (2, 3, date("2024-01-31")),
+            ]
+        );
+        assert!(reminders.iter().all(|r| r.amount_due == 121.0));
+        let states: Vec<u8> = voip_numbers.iter().map(|n| n.current_state).collect();
+        assert_eq!(
+            states,
+            [
+                NUMBER_STATE_ACTIVE,
+                NUMBER_STATE_SUSPENDED,
+                NUMBER_STATE_ACTIVE
+            ]
+        );
+    }
+
+    #[test]
+    fn only_contracts_unpaid_past_threshold_are_terminated() {
+        let scenario = Scenario {
+            dunning_terminate_after_days: Some(60),
+            ..Scenario::default()
+        };
+        let recent = (Utc::now() - Duration::days(10)).to_rfc3339();
+        let mut contracts = [contract(1), contract(2), contract(3), contract(4)];
+        contracts[3].deleted_at = Some(date("2024-02-01"));
+        let mut invoices = vec![
+            invoice(1, 1, &date("2024-01-01"), None),
+            invoice(2, 1, &date("2024-04-01"), None),
+            invoice(3, 2, &date("2024-01-01"), Some(&date("2024-01-10"))),
+            invoice(4, 3, &recent, None),
+            invoice(5, 4, &date("2024-01-01"), None),
+        ];
+        let mut lines: Vec<InvoiceHasItems> = invoices
+            .iter()
+            .map(|i| InvoiceHasItems::new(i.invoice_number, 1, 100.0, 1))
+            .collect();
+
+        terminate_for_non_payment(&mut contracts, &mut invoices, &mut lines, &scenario);
+
+        let deleted: Vec<Option<String>> = contracts.iter().map(|c| c.deleted_at.clone()).collect();
+        assert_eq!(
+            deleted,
+            [
+                Some(date("2024-03-01")),
+                None,
+                None,
+                Some(date("2024-02-01"))
+            ]
+        );
+        let kept: Vec<u64> = invoices.iter().map(|i| i.invoice_number).collect();
+        assert_eq!(kept, [1, 3, 4, 5]);
+        assert!(lines.iter().all(|l| l.invoice_number != 2));
+    }
+}
@@ -0,0 +1,87 @@
+//! Small hand made rows shared by the simulation tests.
+
+use crate::entities::*;
+
+pub fn date(day: &str) -> String {
+    format!("{}T10:00:00+00:00", day)
+}
+
+pub fn contract(cid: u32) -> Contract {
+    let mut contract = Contract::new(
+        cid,
+        format!("Contract {}", cid),
+        cid as i32,
+        format!("contract{}@example.cz", cid),
+        "+420601000000".parse().unwrap(),
+        None,
+        None,
+        None,
+        None,
+    );
+    contract.created_at = Some(date("2020-01-01"));
+    contract
+}
+
+pub fn participant(pid: u32, contract_id: u32) -> Participant {
+    Participant::new(
+        pid,
+        format!("user{}", pid),
+        format!("User {}", pid),
+        ACCESS_LEVEL_OWNER,
+        contract_id,
+        String::new(),
+        None,
+        Some(date("2020-01-01")),
+        None,
+    )
+}
+
+pub fn voip_number(nid: u32, participant_id: Option<u32>, national: u64) -> VoipNumber {
+    VoipNumber::new(
+        Some(nid),
+        PhoneNumber::new(420, national).unwrap(),
+        participant_id,
+        String::new(),
+        NUMBER_STATE_ACTIVE,
+        false,
+        None,
+        Some(date("2020-01-01")),
+        None,
+    )
+}
+
+/// Answered outgoing domestic call rated by price list 1.
+pub fn call(id: u32, number_id: u32, call_date: &str, billsec: u16) -> CallDetailRecord {
+    CallDetailRecord {
+        call_id: Some(id),
+        disposition: DISPOSITION_ANSWERED.to_string(),
+        source_num: PhoneNumber::new(420, 601_000_000 + u64::from(number_id)).unwrap(),
+        destination_num: "+420602999999".parse().unwrap(),
+        length: 5 + billsec,
+        ring_duration: 5,
+        billsec,
+        failure_reason: None,
+        call_date: call_date.to_string(),
+        number_id,
+        incoming_outgoing: false,
+        price_list_id: Some(1),
+        linked_id: id,
+        leg: 1,
+        leg_type: None,
+        price: None,
+        included_seconds: 0,
+    }
+}
+
+/// Domestic price list 1 at 0.10 CZK per second billed by the second.
+pub fn price_lists() -> Vec<PriceList> {
+    vec![PriceList::new(
+        Some(1),
+        420,
+        "420",
+        10,
+        (1, 1),
+        date("2020-01-01"),
+        None,
+    )]
+}
@@ -0,0 +1,176 @@
+use crate::config::Scenario;
+use crate::entities::generators::gen_phone_number;
+use crate::entities::*;
+use chrono::Duration;
+use fake::Fake;
+use std::collections::HashMap;
+
+/// Most legs a single call is split into.
+const MAX_LEGS: u8 = 5;
+
+/// Starts a new leg placed by `number` from `prev`. A forwarded leg starts as
+/// soon as `prev` is answered, a transferred one somewhere during the talk,
+/// which ends `prev`. Either way the new leg ends where `prev` used to.
+fn next_leg(
+    prev: &mut CallDetailRecord,
+    number: PhoneNumber,
+    kind: &str,
+    call_id: u32,
+) -> Option<CallDetailRecord> {
+    let talk = prev.billsec;
+    let offset = if kind == LEG_FORWARD {
+        0
+    } else if talk > 1 {
+        (1..talk).fake::<u16>()
+    } else {
+        return None;
+    };
+    let remaining = talk - offset;
+    if remaining < 2 {
+        return None;
+    }
+    let ring_duration = (1..remaining.min(16)).fake::<u16>();
+    let start = parse_date(&prev.call_date)
+        + Duration::seconds(i64::from(prev.ring_duration) + i64::from(offset));
+    if kind == LEG_TRANSFER {
+        prev.billsec = offset;
+        prev.length = prev.ring_duration + offset;
+    }
+
+    Some(CallDetailRecord {
+        call_id: Some(call_id),
+        disposition: DISPOSITION_ANSWERED.to_string(),
+        source_num: number,
+        destination_num: gen_phone_number(number.country_code),
+        length: remaining,
+        ring_duration,
+        billsec: remaining - ring_duration,
+        failure_reason: None,
+        call_date: start.to_rfc3339(),
+        number_id: prev.number_id,
+        incoming_outgoing: false,
+        price_list_id: None,
+        linked_id: prev.linked_id,
+        leg: prev.leg + 1,
+        leg_type: Some(kind.to_string()),
+        price: None,
+        included_seconds: 0,
+    })
+}
+
+/// Splits answered calls into chains of linked legs. Incoming calls may get
+/// forwarded to a domestic number, any leg may then be transferred to another
+/// one. New legs are outgoing calls of the forwarding or transferring number
+/// and share the `linked_id` of the first leg.
+pub fn link_call_legs(
+    calls: &mut Vec<CallDetailRecord>,
+    voip_numbers: &[VoipNumber],
+    scenario: &Scenario,
+) {
+    use fake::faker::boolean::en::Boolean;
+
+    let numbers: HashMap<u32, PhoneNumber> = voip_numbers
+        .iter()
+        .filter_map(|n| Some((n.number_id?, n.number)))
+        .collect();
+    let mut last_id = calls.iter().filter_map(|c| c.call_id).max().unwrap_or(0);
+    let mut legs: Vec<CallDetailRecord> = vec![];
+
+    for call in calls.iter_mut() {
+        let number = match numbers.get(&call.number_id) {
+            Some(number) if call.disposition == DISPOSITION_ANSWERED => *number,
+            _ => continue,
+        };
+        let mut chain: Vec<CallDetailRecord> = vec![];
+        if call.incoming_outgoing && Boolean(scenario.call_forward_percent).fake() {
+            if let Some(leg) = next_leg(call, number, LEG_FORWARD, last_id + 1) {
+                last_id += 1;
+                chain.push(leg);
+            }
+        }
+        while Boolean(scenario.call_transfer_percent).fake() {
+            let prev = chain.last_mut().unwrap_or(&mut *call);
+            if prev.leg >= MAX_LEGS {
+                break;
+            }
+            match next_leg(prev, number, LEG_TRANSFER, last_id + 1) {
+                Some(leg) => {
+                    last_id += 1;
+                    chain.push(leg);
+                }
+                None => break,
+            }
+        }
+        legs.append(&mut chain);
+    }
+    calls.append(&mut legs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::*;
+    use super::*;
+
+    fn end(call: &CallDetailRecord) -> chrono::DateTime<chrono::FixedOffset> {
+        parse_date(&call.call_date) + Duration::seconds(i64::from(call.length))
+    }
+
+    #[test]
+    fn legs_follow_each_other_and_end_with_the_call() {
+        let scenario = Scenario {
+            call_forward_percent: 50,
+            call_transfer_percent: 80,
+            ..Scenario::default()
+        };
+        let voip_numbers: Vec<VoipNumber> = (1..=10)
+            .map(|nid| voip_number(nid, Some(nid), 601_000_000 + u64::from(nid)))
+            .collect();
+        let mut calls: Vec<CallDetailRecord> = (1..=400)
+            .map(|id| {
+                let mut call = call(id, id % 10 + 1, &date("2024-01-05"), 600);
+                call.incoming_outgoing = id % 2 == 0;
+                call
+            })
+            .collect();
+        let call_ends: HashMap<u32, _> = calls.iter().map(|c| (c.linked_id, end(c))).collect();
+
+        link_call_legs(&mut calls, &voip_numbers, &scenario);
+
+        let mut chains: HashMap<u32, Vec<&CallDetailRecord>> = HashMap::new();
+        for call in &calls {
+            chains.entry(call.linked_id).or_default().push(call);
+        }
+        assert_eq!(chains.len(), 400);
+        assert!(chains.values().any(|c| c.len() > 2), "No call got split");
+        assert!(calls
+            .iter()
+            .any(|c| c.leg_type.as_deref() == Some(LEG_FORWARD)));
+        for chain in chains.values_mut() {
+            chain.sort_by_key(|c| c.leg);
+            let legs: Vec<u8> = chain.iter().map(|c| c.leg).collect();
+            let expected: Vec<u8> = (1..=chain.len() as u8).collect();
+            assert_eq!(legs, expected);
+            assert!(chain.len() <= usize::from(MAX_LEGS));
+
+            for pair in chain.windows(2) {
+                let (prev, leg) = (pair[0], pair[1]);
+                let answered =
+                    parse_date(&prev.call_date) + Duration::seconds(i64::from(prev.ring_duration));
+                let start = parse_date(&leg.call_date);
+                assert!(start >= answered, "{:?} {:?}", prev, leg);
+                match leg.leg_type.as_deref() {
+                    Some(LEG_FORWARD) => {
+                        // A forwarded call keeps ringing through to the end,
+                        // later transfers only cut the forwarded leg.
+                        assert_eq!(start, answered);
+                        assert_eq!(end(prev), call_ends[&prev.linked_id]);
+                    }
+                    Some(LEG_TRANSFER) => assert_eq!(start, end(prev)),
+                    kind => panic!("Unexpected leg type {:?}", kind),
+                }
+                assert!(leg.billsec + leg.ring_duration == leg.length);
+            }
+            assert_eq!(end(chain.last().unwrap()), call_ends[&chain[0].linked_id]);
+        }
+    }
+}
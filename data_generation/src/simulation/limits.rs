@@ -0,0 +1,207 @@
+use super::plans::Tariff;
+use super::*;
+use crate::entities::*;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+pub const NOTIFY_LIMIT: &str = "NOTIFY_LIMIT";
+pub const BALANCE_LIMIT: &str = "BALANCE_LIMIT";
+pub const BLOCKED_DISPOSITION: &str = DISPOSITION_FAILED;
+
+/// Walks all billable calls chronologically, accumulates spend per participant
+/// and contract for each billing period and emits a notification whenever the
+/// spend crosses the contract's `notify_limit` or participant's `balance_limit`.
+/// Calls are priced by the `tariff` as they are charged. With
+/// `block_over_limit` calls placed after the balance limit was reached get
+/// rewritten to a blocked disposition and are not charged.
+pub fn simulate_limits(
+    calls: &mut [CallDetailRecord],
+    voip_numbers: &[VoipNumber],
+    participants: &[Participant],
+    contracts: &[Contract],
+    tariff: &mut Tariff,
+    block_over_limit: bool,
+    last_notification_id: u32,
+) -> Vec<Notification> {
+    let number_owner: HashMap<u32, u32> = voip_numbers
+        .iter()
+        .filter_map(|n| Some((n.number_id?, n.participant_id?)))
+        .collect();
+    let participants: HashMap<u32, &Participant> = participants
+        .iter()
+        .filter_map(|p| Some((p.participant_id?, p)))
+        .collect();
+    let notify_limits: HashMap<u32, Decimal> = contracts
+        .iter()
+        .filter_map(|c| Some((c.contract_id?, c.notify_limit?)))
+        .collect();
+
+    let mut order: Vec<(DateTime<FixedOffset>, usize)> = calls
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.is_billable())
+        .map(|(idx, c)| (parse_date(&c.call_date), idx))
+        .collect();
+    order.sort();
+
+    let mut participant_spend: HashMap<(u32, String), Decimal> = HashMap::new();
+    let mut contract_spend: HashMap<(u32, String), Decimal> = HashMap::new();
+    let mut notifications: Vec<Notification> = vec![];
+    let mut notification_id = last_notification_id;
+
+    for (date, idx) in order {
+        let call = &mut calls[idx];
+        let participant = match number_owner
+            .get(&call.number_id)
+            .and_then(|pid| participants.get(pid))
+        {
+            Some(p) => p,
+            None => continue,
+        };
+        if call.price_list_id.is_none() {
+            continue;
+        }
+        let period = billing_period(&date);
+        let pid = participant.participant_id.unwrap();
+        let cid = participant.contract_id;
+
+        let spent = participant_spend
+            .entry((pid, period.clone()))
+            .or_insert(Decimal::ZERO);
+        if let Some(limit) = participant.balance_limit {
+            if block_over_limit && *spent >= limit {
+                call.disposition = BLOCKED_DISPOSITION.to_string();
+                call.length = call.ring_duration;
+                call.billsec = 0;
+                call.failure_reason = Some(BALANCE_LIMIT.to_string());
+                continue;
+            }
+        }
+        let price = match tariff.charge(call, cid, &date) {
+            Some(price) => price,
+            None => continue,
+        };
+        if let Some(limit) = participant.balance_limit {
+            if *spent < limit && *spent + price >= limit {
+                notification_id += 1;
+                notifications.push(Notification {
+                    notification_id: Some(notification_id),
+                    contract_id: cid,
+                    participant_id: Some(pid),
+                    kind: BALANCE_LIMIT.to_string(),
+                    billing_period: period.clone(),
+                    spent: *spent + price,
+                    limit_amount: limit,
+                    call_id: call.call_id,
+                    created_at: call.call_date.clone(),
+                });
+            }
+        }
+        *spent += price;
+
+        let spent = contract_spend
+            .entry((cid, period.clone()))
+            .or_insert(Decimal::ZERO);
+        if let Some(limit) = notify_limits.get(&cid) {
+            if *spent < *limit && *spent + price >= *limit {
+                notification_id += 1;
+                notifications.push(Notification {
+                    notification_id: Some(notification_id),
+                    contract_id: cid,
+                    participant_id: None,
+                    kind: NOTIFY_LIMIT.to_string(),
+                    billing_period: period,
+                    spent: *spent + price,
+                    limit_amount: *limit,
+                    call_id: call.call_id,
+                    created_at: call.call_date.clone(),
+                });
+            }
+        }
+        *spent += price;
+    }
+    notifications
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::*;
+    use super::*;
+
+    #[test]
+    fn notify_limit_is_reported_once_per_billing_period() {
+        let mut contract = contract(1);
+        contract.notify_limit = Some(Decimal::from(5));
+        let participants = [participant(1, 1)];
+        let voip_numbers = [voip_number(1, Some(1), 601_000_001)];
+        let price_lists = price_lists();
+        let mut tariff = Tariff::new(&price_lists, &[], &[], &[]);
+        // Three CZK a call, the limit is crossed by the second call of a month.
+        let mut calls: Vec<CallDetailRecord> = [
+            "2024-01-05",
+            "2024-01-06",
+            "2024-01-07",
+            "2024-02-05",
+            "2024-02-06",
+            "2024-02-07",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(idx, day)| call(idx as u32 + 1, 1, &date(day), 30))
+        .collect();
+
+        let notifications = simulate_limits(
+            &mut calls,
+            &voip_numbers,
+            &participants,
+            &[contract],
+            &mut tariff,
+            false,
+            0,
+        );
+
+        let notified: Vec<(&str, Option<u32>)> = notifications
+            .iter()
+            .map(|n| (&n.billing_period[..], n.call_id))
+            .collect();
+        assert_eq!(notified, [("2024-01-01", Some(2)), ("2024-02-01", Some(5))]);
+        assert!(notifications.iter().all(|n| n.kind == NOTIFY_LIMIT));
+        assert!(calls.iter().all(|c| c.price == Some(Decimal::from(3))));
+    }
+
+    #[test]
+    fn calls_over_balance_limit_are_blocked_and_not_rated() {
+        let mut participant = participant(1, 1);
+        participant.balance_limit = Some(Decimal::from(5));
+        let voip_numbers = [voip_number(1, Some(1), 601_000_001)];
+        let price_lists = price_lists();
+        let mut tariff = Tariff::new(&price_lists, &[], &[], &[]);
+        let mut calls: Vec<CallDetailRecord> = (1..=4)
+            .map(|id| call(id, 1, &date(&format!("2024-01-0{}", id)), 30))
+            .collect();
+
+        let notifications = simulate_limits(
+            &mut calls,
+            &voip_numbers,
+            &[participant],
+            &[contract(1)],
+            &mut tariff,
+            true,
+            0,
+        );
+
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].kind, BALANCE_LIMIT);
+        assert_eq!(notifications[0].call_id, Some(2));
+        for call in &calls[..2] {
+            assert_eq!(call.disposition, DISPOSITION_ANSWERED);
+            assert_eq!(call.price, Some(Decimal::from(3)));
+        }
+        for call in &calls[2..] {
+            assert_eq!(call.disposition, BLOCKED_DISPOSITION);
+            assert_eq!(call.failure_reason.as_deref(), Some(BALANCE_LIMIT));
+            assert_eq!(call.billsec, 0);
+            assert_eq!(call.price, None);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+pub use crate::entities::parse_date;
+use chrono::{DateTime, FixedOffset};
+
+pub mod billing;
+pub mod channels;
+pub mod dunning;
+#[cfg(test)]
+mod fixtures;
+pub mod legs;
+pub mod limits;
+pub mod numbers;
+pub mod plans;
+pub mod policy;
+pub mod rating;
+
+/// First day of the calendar month the date falls into.
+pub fn billing_period(date: &DateTime<FixedOffset>) -> String {
+    date.format("%Y-%m-01").to_string()
+}
@@ -0,0 +1,243 @@
+use super::*;
+use crate::config::Scenario;
+use crate::entities::number_plan::NumberPlan;
+use crate::entities::*;
+use chrono::{Duration, Utc};
+use fake::faker::boolean::en::Boolean;
+use fake::Fake;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Pool of numbers released from earlier assignments, each becoming reusable
+/// once its quarantine is over.
+struct NumberPool {
+    quarantined: BinaryHeap<Reverse<(DateTime<Utc>, PhoneNumber)>>,
+    reusable: Vec<PhoneNumber>,
+}
+
+impl NumberPool {
+    fn release(&mut self, number: PhoneNumber, until: DateTime<Utc>) {
+        self.quarantined.push(Reverse((until, number)));
+    }
+
+    /// Random reusable number of the country at `at`.
+    fn take_reusable(&mut self, at: DateTime<Utc>, country_code: u16) -> Option<PhoneNumber> {
+        while let Some(Reverse((until, number))) = self.quarantined.peek().copied() {
+            if until > at {
+                break;
+            }
+            self.quarantined.pop();
+            self.reusable.push(number);
+        }
+        let candidates: Vec<usize> = (0..self.reusable.len())
+            .filter(|idx| self.reusable[*idx].country_code == country_code)
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let idx = candidates[(0..candidates.len()).fake::<usize>()];
+        Some(self.reusable.swap_remove(idx))
+    }
+}
+
+/// Replays number assignments chronologically. Released numbers go to
+/// quarantine for the configured number of days and later assignments or
+/// pending requests in the same country may pick them up again, so the same
+/// `number` can appear on several rows with non-overlapping lifespans.
+/// Numbers of DID blocks stay with their contract and never enter the pool.
+/// Keeps `current_state`, `quarantine_until`, `activated` and `deleted_at` of
+/// every row consistent.
+pub fn simulate_number_lifecycle(
+    voip_numbers: &mut [VoipNumber],
+    plan: &NumberPlan,
+    scenario: &Scenario,
+) {
+    let now = Utc::now();
+    let quarantine = Duration::days(i64::from(scenario.number_quarantine_days));
+    let mut pool = NumberPool {
+        quarantined: BinaryHeap::new(),
+        reusable: vec![],
+    };
+
+    let mut order: Vec<(DateTime<Utc>, usize)> = voip_numbers
+        .iter()
+        .enumerate()
+        .map(|(idx, n)| (n.lifespan().map_or(now, |l| l.start), idx))
+        .collect();
+    order.sort();
+
+    for (start, idx) in order {
+        let number = &mut voip_numbers[idx];
+        let in_block = plan.in_block(&number.number);
+        let assigned = number.current_state != NUMBER_STATE_FREE;
+        if assigned && !in_block && Boolean(scenario.number_reuse_percent).fake() {
+            if let Some(reused) = pool.take_reusable(start, number.number.country_code) {
+                number.number = reused;
+            }
+        }
+
+        let end = match number.lifespan().and_then(|l| l.end) {
+            Some(end) => end,
+            None => continue,
+        };
+        let until = end + quarantine;
+        number.quarantine_until = Some(until.to_rfc3339());
+        number.current_state = if until > now {
+            NUMBER_STATE_QUARANTINED
+        } else {
+            NUMBER_STATE_FREE
+        };
+        if !in_block {
+            pool.release(number.number, until);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{date, voip_number};
+    use super::*;
+    use crate::entities::generators::gen_lifespan;
+    use std::collections::HashMap;
+
+    /// Numbers of 150 participants with random lifespans, half of them
+    /// released, plus a few never activated free numbers.
+    fn numbers() -> Vec<VoipNumber> {
+        let mut numbers: Vec<VoipNumber> = (1..=150)
+            .map(|nid| {
+                let lifespan = gen_lifespan(
+                    &Lifespan {
+                        start: Lifespan::epoch(),
+                        end: None,
+                    },
+                    50,
+                );
+                let mut number = voip_number(nid, Some(nid), 601_000_000 + u64::from(nid));
+                number.activated = Some(lifespan.start.to_rfc3339());
+                number.deleted_at = lifespan.end.map(|d| d.to_rfc3339());
+                number
+            })
+            .collect();
+        for nid in 151..=155 {
+            let mut number = voip_number(nid, None, 601_000_000 + u64::from(nid));
+            number.current_state = NUMBER_STATE_FREE;
+            number.activated = None;
+            numbers.push(number);
+        }
+        numbers
+    }
+
+    fn scenario() -> Scenario {
+        Scenario {
+            number_reuse_percent: 80,
+            number_quarantine_days: 30,
+            ..Scenario::default()
+        }
+    }
+
+    #[test]
+    fn reused_numbers_never_overlap() {
+        let scenario = scenario();
+        let mut numbers = numbers();
+        simulate_number_lifecycle(&mut numbers, &NumberPlan::new(vec![]), &scenario);
+
+        let mut by_number: HashMap<PhoneNumber, Vec<Lifespan>> = HashMap::new();
+        for number in &numbers {
+            if let Some(lifespan) = number.lifespan() {
+                by_number.entry(number.number).or_default().push(lifespan);
+            }
+        }
+        assert!(by_number.values().any(|l| l.len() > 1), "Nothing reused");
+        let quarantine = Duration::days(i64::from(scenario.number_quarantine_days));
+        for lifespans in by_number.values_mut() {
+            lifespans.sort_by_key(|l| l.start);
+            for pair in lifespans.windows(2) {
+                let end = pair[0].end.expect("Reused number still assigned");
+                assert!(end + quarantine <= pair[1].start, "{:?}", pair);
+            }
+        }
+    }
+
+    #[test]
+    fn states_agree_with_dates_and_owner() {
+        let mut numbers = numbers();
+        simulate_number_lifecycle(&mut numbers, &NumberPlan::new(vec![]), &scenario());
+
+        let now = Utc::now();
+        for number in &numbers {
+            let until = number.quarantine_until.as_deref().map(parse_date);
+            let deleted = number.deleted_at.as_deref().map(parse_date);
+            match number.current_state {
+                NUMBER_STATE_ACTIVE => {
+                    assert!(number.participant_id.is_some(), "{:?}", number);
+                    assert!(number.activated.is_some(), "{:?}", number);
+                    assert!(deleted.is_none() && until.is_none(), "{:?}", number);
+                }
+                NUMBER_STATE_QUARANTINED => {
+                    assert!(until.unwrap() > deleted.unwrap(), "{:?}", number);
+                    assert!(until.unwrap() > now, "{:?}", number);
+                }
+                NUMBER_STATE_FREE if number.activated.is_none() => {
+                    assert!(number.participant_id.is_none(), "{:?}", number);
+                    assert!(deleted.is_none() && until.is_none(), "{:?}", number);
+                }
+                NUMBER_STATE_FREE => {
+                    assert!(until.unwrap() > deleted.unwrap(), "{:?}", number);
+                    assert!(until.unwrap() <= now, "{:?}", number);
+                }
+                state => panic!("Unexpected state {} of {:?}", state, number),
+            }
+        }
+    }
+
+    /// A number released long ago is free again, then taken by an approved
+    /// request and released into quarantine, or taken by a pending request
+    /// while nothing else needs it. Numbers of turned down requests stay free
+    /// and out of the pool.
+    #[test]
+    fn states_follow_the_request_workflow() {
+        let scenario = Scenario {
+            number_reuse_percent: 100,
+            ..scenario()
+        };
+        let recently = (Utc::now() - Duration::days(5)).to_rfc3339();
+        let rows = || {
+            let mut released = voip_number(1, Some(1), 601_000_001);
+            released.activated = Some(date("2020-01-01"));
+            released.deleted_at = Some(date("2020-06-01"));
+            let mut approved = voip_number(2, Some(2), 601_000_002);
+            approved.activated = Some(date("2021-01-01"));
+            approved.deleted_at = Some(recently.clone());
+            let mut pending = voip_number(3, Some(3), 601_000_003);
+            pending.current_state = NUMBER_STATE_REQUESTED;
+            pending.activated = None;
+            let mut rejected = voip_number(4, None, 601_000_004);
+            rejected.current_state = NUMBER_STATE_FREE;
+            rejected.activated = None;
+            vec![released, approved, pending, rejected]
+        };
+        let numbers = rows();
+        let (x, y, w) = (numbers[0].number, numbers[2].number, numbers[3].number);
+
+        // FREE -> REQUESTED
+        let mut numbers = rows();
+        numbers.remove(1);
+        simulate_number_lifecycle(&mut numbers, &NumberPlan::new(vec![]), &scenario);
+        assert_eq!(numbers[0].current_state, NUMBER_STATE_FREE);
+        assert_eq!(numbers[1].current_state, NUMBER_STATE_REQUESTED);
+        assert_eq!(numbers[1].number, x);
+        assert_eq!(numbers[2].current_state, NUMBER_STATE_FREE);
+        assert_eq!(numbers[2].number, w);
+
+        // REQUESTED -> ACTIVE -> QUARANTINED, the pool has nothing left.
+        let mut numbers = rows();
+        simulate_number_lifecycle(&mut numbers, &NumberPlan::new(vec![]), &scenario);
+        assert_eq!(numbers[0].current_state, NUMBER_STATE_FREE);
+        assert_eq!(numbers[1].current_state, NUMBER_STATE_QUARANTINED);
+        assert_eq!(numbers[1].number, x);
+        assert_eq!(numbers[2].current_state, NUMBER_STATE_REQUESTED);
+        assert_eq!(numbers[2].number, y);
+        assert_eq!(numbers[3].current_state, NUMBER_STATE_FREE);
+        assert_eq!(numbers[3].number, w);
+    }
+}
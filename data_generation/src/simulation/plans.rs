@@ -0,0 +1,157 @@
+use super::*;
+use crate::entities::generators::PREMIUM_PREFIXES;
+use crate::entities::*;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Prices calls by the plan the calling contract was subscribed to at the call
+/// date. Billed seconds are first taken from the plan's included minutes,
+/// which renew every billing period, the rest is charged at the plan's
+/// override for the price list prefix or the price list's own price. Premium
+/// prefixes are always charged in full at the price list's price.
+pub struct Tariff<'a> {
+    price_lists: HashMap<u32, &'a PriceList>,
+    plans: HashMap<u32, &'a Plan>,
+    rates: HashMap<(u32, &'a str), u16>,
+    subscriptions: HashMap<u32, Vec<&'a Subscription>>,
+    /// Included seconds left per contract, plan and billing period.
+    included: HashMap<(u32, u32, String), u32>,
+}
+
+impl<'a> Tariff<'a> {
+    pub fn new(
+        price_lists: &'a [PriceList],
+        plans: &'a [Plan],
+        rates: &'a [PlanRate],
+        subscriptions: &'a [Subscription],
+    ) -> Tariff<'a> {
+        let mut by_contract: HashMap<u32, Vec<&Subscription>> = HashMap::new();
+        for subscription in subscriptions {
+            by_contract
+                .entry(subscription.contract_id)
+                .or_default()
+                .push(subscription);
+        }
+        Tariff {
+            price_lists: price_lists
+                .iter()
+                .filter_map(|p| Some((p.price_list_id?, p)))
+                .collect(),
+            plans: plans.iter().map(|p| (p.plan_id, p)).collect(),
+            rates: rates
+                .iter()
+                .map(|r| ((r.plan_id, &r.prefix[..]), r.price_per_second))
+                .collect(),
+            subscriptions: by_contract,
+            included: HashMap::new(),
+        }
+    }
+
+    fn plan_at(&self, contract_id: u32, date: &DateTime<FixedOffset>) -> Option<&'a Plan> {
+        let subscription = self.subscriptions.get(&contract_id)?.iter().find(|s| {
+            parse_date(&s.valid_from) <= *date
+                && s.valid_to.as_ref().is_none_or(|to| *date < parse_date(to))
+        })?;
+        self.plans.get(&subscription.plan_id).copied()
+    }
+
+    /// Prices a billable call of the contract and records the price and the
+    /// included seconds it used on the call. Calls must come in chronological
+    /// order, unrated ones are left unpriced.
+    pub fn charge(
+        &mut self,
+        call: &mut CallDetailRecord,
+        contract_id: u32,
+        date: &DateTime<FixedOffset>,
+    ) -> Option<Decimal> {
+        let list = *self.price_lists.get(&call.price_list_id?)?;
+        let billed = list.billed_seconds(call.billsec);
+        let mut price_per_second = list.price_per_second;
+        let mut included = 0;
+        let premium = PREMIUM_PREFIXES.contains(&&list.prefix[..]);
+        if let Some(plan) = self.plan_at(contract_id, date).filter(|_| !premium) {
+            if let Some(rate) = self.rates.get(&(plan.plan_id, &list.prefix[..])) {
+                price_per_second = *rate;
+            }
+            // Calls the plan makes free leave the included minutes alone.
+            if price_per_second > 0 {
+                let left = self
+                    .included
+                    .entry((contract_id, plan.plan_id, billing_period(date)))
+                    .or_insert(plan.included_minutes * 60);
+                included = billed.min(*left);
+                *left -= included;
+            }
+        }
+        let price = units_price(billed - included, price_per_second);
+        call.included_seconds = included;
+        call.price = Some(price);
+        Some(price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::generators::gen_cdr;
+    use crate::entities::traffic::CallOutcome;
+    use chrono::Utc;
+
+    fn call(id: u32, price_list_id: u32, billsec: u16) -> CallDetailRecord {
+        let mut call = gen_cdr(
+            id,
+            420,
+            "+420601123456".parse().unwrap(),
+            1,
+            Utc::now(),
+            CallOutcome {
+                disposition: DISPOSITION_ANSWERED,
+                ring_duration: 0,
+                billsec,
+            },
+        );
+        call.price_list_id = Some(price_list_id);
+        call
+    }
+
+    #[test]
+    fn premium_calls_are_charged_outside_included_minutes() {
+        let epoch = Lifespan::epoch().to_rfc3339();
+        let price_lists = [
+            PriceList::new(Some(1), 420, "420", 10, (1, 1), epoch.clone(), None),
+            PriceList::new(Some(2), 420, "42090", 300, (60, 60), epoch.clone(), None),
+        ];
+        let plans = [Plan {
+            plan_id: 1,
+            name: String::from("Standard"),
+            monthly_fee: Decimal::new(19900, 2),
+            included_minutes: 100,
+            invoice_item_id: 1,
+        }];
+        let rates = [PlanRate {
+            plan_id: 1,
+            prefix: String::from("42090"),
+            price_per_second: 0,
+        }];
+        let subscriptions = [Subscription {
+            subscription_id: 1,
+            contract_id: 1,
+            plan_id: 1,
+            valid_from: epoch,
+            valid_to: None,
+        }];
+        let mut tariff = Tariff::new(&price_lists, &plans, &rates, &subscriptions);
+        let date = parse_date(&Utc::now().to_rfc3339());
+
+        let mut premium = call(1, 2, 90);
+        assert_eq!(
+            tariff.charge(&mut premium, 1, &date),
+            Some(Decimal::new(360, 0))
+        );
+        assert_eq!(premium.included_seconds, 0);
+
+        let mut domestic = call(2, 1, 90);
+        assert_eq!(tariff.charge(&mut domestic, 1, &date), Some(Decimal::ZERO));
+        assert_eq!(domestic.included_seconds, 90);
+    }
+}
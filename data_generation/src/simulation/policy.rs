@@ -0,0 +1,112 @@
+use crate::entities::generators::gen_phone_number;
+use crate::entities::*;
+use fake::Fake;
+use std::collections::HashMap;
+
+pub const FOREIGN_BLOCK: &str = "FOREIGN_BLOCK";
+
+/// Keeps numbers with `foreign_block` from calling abroad. Their outgoing
+/// international calls either stay as failed attempts, with `attempt_percent`
+/// chance, or are turned into calls to a domestic number.
+pub fn enforce_foreign_block(
+    calls: &mut [CallDetailRecord],
+    voip_numbers: &[VoipNumber],
+    attempt_percent: u8,
+) {
+    use fake::faker::boolean::en::Boolean;
+
+    let blocked: HashMap<u32, u16> = voip_numbers
+        .iter()
+        .filter(|n| n.foreign_block)
+        .filter_map(|n| Some((n.number_id?, n.number.country_code)))
+        .collect();
+    for call in calls.iter_mut().filter(|c| !c.incoming_outgoing) {
+        let domestic = match blocked.get(&call.number_id) {
+            Some(pcc) if *pcc != call.destination_num.country_code => *pcc,
+            _ => continue,
+        };
+        if Boolean(attempt_percent).fake() {
+            call.disposition = DISPOSITION_FAILED.to_string();
+            call.length = 0;
+            call.ring_duration = 0;
+            call.billsec = 0;
+            call.failure_reason = Some(FOREIGN_BLOCK.to_string());
+        } else {
+            call.destination_num = gen_phone_number(domestic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::generators::{gen_cdr, gen_price_lists};
+    use crate::entities::traffic::CallOutcome;
+    use crate::simulation::rating::rate_calls;
+    use chrono::Utc;
+
+    #[test]
+    fn blocked_numbers_are_never_billed_for_foreign_calls() {
+        let voip_numbers: Vec<VoipNumber> = (1..=20)
+            .map(|nid| {
+                VoipNumber::new(
+                    Some(nid),
+                    gen_phone_number(420),
+                    Some(nid),
+                    String::new(),
+                    NUMBER_STATE_ACTIVE,
+                    nid % 2 == 0,
+                    None,
+                    None,
+                    None,
+                )
+            })
+            .collect();
+        let mut calls: Vec<CallDetailRecord> = (1..=2000)
+            .map(|id| {
+                let number = &voip_numbers[id as usize % voip_numbers.len()];
+                gen_cdr(
+                    id,
+                    if id % 3 == 0 { 420 } else { 49 },
+                    number.number,
+                    number.number_id.unwrap(),
+                    Utc::now(),
+                    CallOutcome {
+                        disposition: DISPOSITION_ANSWERED,
+                        ring_duration: 5,
+                        billsec: 60,
+                    },
+                )
+            })
+            .collect();
+        let price_lists = gen_price_lists(0);
+
+        enforce_foreign_block(&mut calls, &voip_numbers, 50);
+        rate_calls(&mut calls, &price_lists);
+
+        let blocked: Vec<u32> = voip_numbers
+            .iter()
+            .filter(|n| n.foreign_block)
+            .map(|n| n.number_id.unwrap())
+            .collect();
+        let country: HashMap<u32, u16> = price_lists
+            .iter()
+            .map(|p| (p.price_list_id.unwrap(), p.phone_country_code))
+            .collect();
+        let violations = calls
+            .iter()
+            .filter(|c| !c.incoming_outgoing && c.billsec > 0 && blocked.contains(&c.number_id))
+            .filter(|c| {
+                c.destination_num.country_code != 420
+                    || c.price_list_id.is_some_and(|id| country[&id] != 420)
+            })
+            .count();
+        assert_eq!(violations, 0);
+        assert!(calls
+            .iter()
+            .any(|c| c.failure_reason.as_deref() == Some(FOREIGN_BLOCK)));
+        assert!(calls.iter().any(|c| !c.incoming_outgoing
+            && !blocked.contains(&c.number_id)
+            && c.destination_num.country_code == 49));
+    }
+}
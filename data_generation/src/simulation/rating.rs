@@ -0,0 +1,62 @@
+use super::*;
+use crate::entities::*;
+
+/// Price list of the service valid at `date` with the longest prefix matching
+/// `number`.
+fn price_list_for<'a>(
+    price_lists: &'a [PriceList],
+    service: &str,
+    number: &PhoneNumber,
+    date: &DateTime<FixedOffset>,
+) -> Option<&'a PriceList> {
+    price_lists
+        .iter()
+        .filter(|p| p.service == service && p.matches(number) && p.is_valid_at(date))
+        .max_by_key(|p| p.prefix.len())
+}
+
+/// Resolves the price list of every call by the longest prefix matching the
+/// remote number among the lists valid at the call date. Calls to
+/// destinations without a price list stay unrated.
+pub fn rate_calls(calls: &mut [CallDetailRecord], price_lists: &[PriceList]) {
+    for call in calls.iter_mut() {
+        let date = parse_date(&call.call_date);
+        call.price_list_id = price_list_for(price_lists, SERVICE_VOICE, call.remote_num(), &date)
+            .and_then(|p| p.price_list_id);
+    }
+}
+
+/// Rates messages like calls and charges every part of an outgoing one.
+pub fn rate_sms(messages: &mut [SmsRecord], price_lists: &[PriceList]) {
+    for sms in messages.iter_mut() {
+        let date = parse_date(&sms.sent_at);
+        let list = price_list_for(price_lists, SERVICE_SMS, sms.remote_num(), &date);
+        sms.price_list_id = list.and_then(|p| p.price_list_id);
+        sms.price = list
+            .filter(|_| !sms.incoming)
+            .map(|p| units_price(u32::from(sms.parts), p.price_per_second));
+    }
+}
+
+/// Rates data sessions by the country of the network they used and charges
+/// every started megabyte.
+pub fn rate_data_sessions(sessions: &mut [DataSession], price_lists: &[PriceList]) {
+    for session in sessions.iter_mut() {
+        let date = parse_date(&session.started_at);
+        let list = price_lists
+            .iter()
+            .filter(|p| {
+                p.service == SERVICE_DATA
+                    && p.phone_country_code == session.phone_country_code
+                    && p.is_valid_at(&date)
+            })
+            .max_by_key(|p| p.prefix.len());
+        session.price_list_id = list.and_then(|p| p.price_list_id);
+        session.price = list.map(|p| {
+            units_price(
+                session.billed_megabytes().min(u64::from(u32::MAX)) as u32,
+                p.price_per_second,
+            )
+        });
+    }
+}